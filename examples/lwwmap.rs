@@ -0,0 +1,31 @@
+extern crate crdts;
+
+use crdts::{CmRDT, CvRDT, LWWMap};
+
+fn main() {
+    // markers here are (timestamp, actor) pairs so they are globally unique
+    let mut laptop: LWWMap<&str, &str, (u64, u8), u8> = LWWMap::new();
+    let mut phone = laptop.clone();
+
+    // both devices race to set the same key
+    laptop.apply(laptop.set(
+        "status",
+        "at work",
+        (100, 1),
+        laptop.read_ctx().derive_add_ctx(1),
+    ));
+    phone.apply(phone.set(
+        "status",
+        "on the train",
+        (105, 2),
+        phone.read_ctx().derive_add_ctx(2),
+    ));
+
+    // once they sync, the write with the greatest marker wins on both
+    let laptop_snapshot = laptop.clone();
+    laptop.merge(phone.clone());
+    phone.merge(laptop_snapshot);
+
+    assert_eq!(laptop, phone);
+    assert_eq!(laptop.get(&"status"), Some(&"on the train"));
+}
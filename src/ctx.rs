@@ -1,20 +1,27 @@
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 
 use serde::{Deserialize, Serialize};
 
-use crate::{CmRDT, Dot, VClock};
+use crate::{CmRDT, CvRDT, Dot, VClock};
 
 /// ReadCtx's are used to extract data from CRDT's while maintaining some causal history.
 /// You should store ReadCtx's close to where mutation is exposed to the user.
 ///
 /// e.g. Ship ReadCtx to the clients, then derive an Add/RmCtx and ship that back to
 /// where the CRDT is stored to perform the mutation operation.
+///
+/// The two clocks serve different writes: `add_clock` is the clock of the
+/// whole CRDT, which a new write must succeed, while `rm_clock` covers only
+/// what was read, which is what a remove drops. Derive contexts with
+/// `derive_add_ctx` and `derive_rm_ctx` rather than building them from the
+/// clocks by hand, so that each write gets the right one.
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ReadCtx<V, A: Ord> {
-    /// clock used to derive an AddCtx
+    /// clock used to derive an AddCtx, see `ReadCtx::add_clock`
     pub add_clock: VClock<A>,
 
-    /// clock used to derive an RmCtx
+    /// clock used to derive an RmCtx, see `ReadCtx::rm_clock`
     pub rm_clock: VClock<A>,
 
     /// the data read from the CRDT
@@ -38,6 +45,70 @@ pub struct RmCtx<A: Ord> {
     pub clock: VClock<A>,
 }
 
+/// WriteCtx holds either an AddCtx or an RmCtx, for code that handles a
+/// stream of mixed writes, see `Map::write`.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum WriteCtx<A: Ord> {
+    /// The write adds information
+    Add(AddCtx<A>),
+    /// The write removes information
+    Rm(RmCtx<A>),
+}
+
+impl<A: Ord> From<AddCtx<A>> for WriteCtx<A> {
+    fn from(ctx: AddCtx<A>) -> Self {
+        WriteCtx::Add(ctx)
+    }
+}
+
+impl<A: Ord> From<RmCtx<A>> for WriteCtx<A> {
+    fn from(ctx: RmCtx<A>) -> Self {
+        WriteCtx::Rm(ctx)
+    }
+}
+
+impl<V, A: Ord> ReadCtx<V, A> {
+    /// The clock of the whole CRDT at the time of the read.
+    ///
+    /// Adds and updates are built on this clock, see `derive_add_ctx`: the
+    /// dot of a new write has to follow every dot the CRDT has seen. A dot
+    /// derived from a smaller clock, such as `rm_clock`, may already have
+    /// been used, and the write is then ignored as a duplicate.
+    pub fn add_clock(&self) -> &VClock<A> {
+        &self.add_clock
+    }
+
+    /// The clock of the data that was read, e.g. the clock of a single
+    /// member or entry.
+    ///
+    /// Removes are built on this clock, see `derive_rm_ctx`: a remove drops
+    /// the dots its clock covers, and this clock covers just the dots behind
+    /// the data that was read, keeping remove ops small.
+    ///
+    /// # Examples
+    /// ```
+    /// use crdts::{CmRDT, Dot, Orswot, VClock};
+    ///
+    /// let mut set = Orswot::new();
+    /// set.apply(set.add("a", set.read_ctx().derive_add_ctx(1)));
+    /// set.apply(set.add("b", set.read_ctx().derive_add_ctx(1)));
+    ///
+    /// let read = set.contains(&"a");
+    /// assert_eq!(read.add_clock(), &VClock::from(Dot::new(1, 2)));
+    /// assert_eq!(read.rm_clock(), &VClock::from(Dot::new(1, 1)));
+    /// set.apply(set.rm("a", read.derive_rm_ctx()));
+    /// assert!(!set.contains(&"a").val());
+    /// ```
+    pub fn rm_clock(&self) -> &VClock<A> {
+        &self.rm_clock
+    }
+
+    /// The data read from the CRDT.
+    pub fn val(&self) -> &V {
+        &self.val
+    }
+}
+
 impl<V, A: Ord + Clone + Debug> ReadCtx<V, A> {
     /// Derives an AddCtx for a given actor from a ReadCtx
     pub fn derive_add_ctx(self, actor: A) -> AddCtx<A> {
@@ -66,3 +137,44 @@ impl<V, A: Ord + Clone + Debug> ReadCtx<V, A> {
         )
     }
 }
+
+impl<K: Ord, V, A: Ord + Clone + Debug> ReadCtx<BTreeMap<K, V>, A> {
+    /// Collects per-entry ReadCtx's (e.g. from `Map::iter`) into a single
+    /// ReadCtx over a `BTreeMap`, the clocks of the result are the join of
+    /// the clocks of every entry.
+    ///
+    /// # Examples
+    /// ```
+    /// use crdts::{CmRDT, Map, MVReg};
+    /// use crdts::ctx::ReadCtx;
+    ///
+    /// let mut map: Map<u8, MVReg<&str, u8>, u8> = Map::new();
+    /// map.apply(map.update(1, map.read_ctx().derive_add_ctx(7), |r, c| r.write("a", c)));
+    /// map.apply(map.update(2, map.read_ctx().derive_add_ctx(9), |r, c| r.write("b", c)));
+    ///
+    /// let snapshot = ReadCtx::collect_entries(map.iter());
+    /// assert_eq!(snapshot.val.len(), 2);
+    /// assert_eq!(snapshot.rm_clock, map.read_ctx().rm_clock);
+    ///
+    /// // a single remove covering everything in the snapshot
+    /// map.apply(map.rm(1, snapshot.derive_rm_ctx()));
+    /// ```
+    pub fn collect_entries(entries: impl IntoIterator<Item = ReadCtx<(K, V), A>>) -> Self {
+        let mut collected = ReadCtx {
+            add_clock: VClock::new(),
+            rm_clock: VClock::new(),
+            val: BTreeMap::new(),
+        };
+        for ReadCtx {
+            add_clock,
+            rm_clock,
+            val: (key, val),
+        } in entries
+        {
+            collected.add_clock.merge(add_clock);
+            collected.rm_clock.merge(rm_clock);
+            collected.val.insert(key, val);
+        }
+        collected
+    }
+}
@@ -74,6 +74,12 @@ impl<A> From<(A, u64)> for Dot<A> {
     }
 }
 
+impl<A> From<Dot<A>> for (A, u64) {
+    fn from(dot: Dot<A>) -> Self {
+        (dot.actor, dot.counter)
+    }
+}
+
 #[cfg(feature = "quickcheck")]
 use quickcheck::{Arbitrary, Gen};
 
@@ -4,7 +4,7 @@ use core::fmt::Debug;
 use num::bigint::BigUint;
 use serde::{Deserialize, Serialize};
 
-use crate::{CmRDT, CvRDT, Dot, ResetRemove, VClock};
+use crate::{CausalContext, CmRDT, Concurrency, CvRDT, Dot, LogicalEq, ResetRemove, VClock};
 
 /// `GCounter` is a grow-only witnessed counter.
 ///
@@ -63,12 +63,26 @@ impl<A: Ord + Clone + Debug> CvRDT for GCounter<A> {
     }
 }
 
+impl<A: Ord> Concurrency for GCounter<A> {}
+
 impl<A: Ord> ResetRemove<A> for GCounter<A> {
     fn reset_remove(&mut self, clock: &VClock<A>) {
         self.inner.reset_remove(clock);
     }
 }
 
+impl<A: Ord> LogicalEq for GCounter<A> {
+    fn logically_eq(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl<A: Ord + Clone> CausalContext<A> for GCounter<A> {
+    fn causal_context(&self) -> VClock<A> {
+        self.inner.clone()
+    }
+}
+
 impl<A: Ord + Clone> GCounter<A> {
     /// Produce a new `GCounter`.
     pub fn new() -> Self {
@@ -8,7 +8,8 @@ use std::collections::BTreeSet;
 
 use serde::{Deserialize, Serialize};
 
-use crate::{CmRDT, CvRDT, Identifier};
+use crate::ctx::{AddCtx, ReadCtx};
+use crate::{CmRDT, CvRDT, Dot, Identifier, OrdDot, VClock};
 
 /// Operations that can be performed on a List
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -69,7 +70,7 @@ impl<T: Ord + Clone> GList<T> {
     }
 
     /// Iterate over the elements of the list
-    pub fn iter(&self) -> std::collections::btree_set::Iter<Identifier<T>> {
+    pub fn iter(&self) -> std::collections::btree_set::Iter<'_, Identifier<T>> {
         self.list.iter()
     }
 
@@ -132,6 +133,43 @@ impl<T: Ord + Clone> GList<T> {
     }
 }
 
+/// A GList used as an append-only log, each element is stored along with
+/// the dot it was appended with.
+impl<T: Ord + Clone, A: Ord + Clone> GList<(OrdDot<A>, T)> {
+    /// Retrieve the current read context, its clock holds the latest dot
+    /// each actor appended with.
+    pub fn read_ctx(&self) -> ReadCtx<(), A> {
+        let clock = VClock::from_dots(self.list.iter().map(|id| Dot::from(id.value().0.clone())));
+        ReadCtx {
+            add_clock: clock.clone(),
+            rm_clock: clock,
+            val: (),
+        }
+    }
+
+    /// Generate an Op to append the given element to the end of the list.
+    ///
+    /// Equal elements appended concurrently are kept apart by their dots,
+    /// and concurrent appends are ordered by `(dot, element)`, so every
+    /// replica will read the same order once they've seen the same appends.
+    ///
+    /// ```rust
+    /// use crdts::{CmRDT, CvRDT, GList};
+    ///
+    /// let mut log_a = GList::new();
+    /// let mut log_b = log_a.clone();
+    /// log_a.apply(log_a.append("login", log_a.read_ctx().derive_add_ctx('A')));
+    /// log_b.apply(log_b.append("login", log_b.read_ctx().derive_add_ctx('B')));
+    ///
+    /// log_a.merge(log_b);
+    /// let events: Vec<_> = log_a.iter().map(|id| id.value().1).collect();
+    /// assert_eq!(events, ["login", "login"]);
+    /// ```
+    pub fn append(&self, elem: T, ctx: AddCtx<A>) -> Op<(OrdDot<A>, T)> {
+        self.insert_after(self.last(), (ctx.dot.into(), elem))
+    }
+}
+
 impl<T: Ord> CmRDT for GList<T> {
     type Op = Op<T>;
     type Validation = Infallible;
@@ -0,0 +1,299 @@
+use std::collections::HashSet;
+use std::fmt::{self, Debug, Display};
+use std::hash::Hash;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ctx::{AddCtx, ReadCtx, RmCtx};
+use crate::orswot::{self, Orswot};
+use crate::{CmRDT, CvRDT, DotRange, VClock};
+
+/// `Graph` is a directed graph whose vertices and edges are kept in two
+/// `Orswot`s, edges being pairs of vertices.
+///
+/// Both sets are add-biased, so a vertex can be removed on one replica while
+/// another concurrently adds an edge to it. Rather than dropping such edges
+/// on merge, reads only show the edges whose endpoints are both present:
+/// `edges` and `contains_edge` never return a dangling edge, and the edge
+/// shows up again if its missing endpoint is added back.
+///
+/// # Examples
+///
+/// ```
+/// use crdts::{CmRDT, Graph};
+///
+/// let mut g = Graph::new();
+/// g.apply(g.add_vertex("a", g.read_ctx().derive_add_ctx(1)));
+/// g.apply(g.add_vertex("b", g.read_ctx().derive_add_ctx(1)));
+/// g.apply(g.add_edge("a", "b", g.read_ctx().derive_add_ctx(1)));
+/// assert!(g.contains_edge(&"a", &"b").val);
+///
+/// g.apply(g.remove_vertex("b", g.contains_vertex(&"b").derive_rm_ctx()));
+/// assert!(!g.contains_edge(&"a", &"b").val);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Graph<V: Hash + Eq, A: Ord + Hash> {
+    vertices: Orswot<V, A>,
+    edges: Orswot<(V, V), A>,
+}
+
+/// An Op which is produced from editing the graph.
+/// Ship these ops to other replicas to have them sync up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Op<V, A: Ord + Hash> {
+    /// Add or remove vertices
+    Vertices(orswot::Op<V, A>),
+    /// Add or remove edges
+    Edges(orswot::Op<(V, V), A>),
+    /// Remove a vertex along with the edges to and from it
+    RmVertex {
+        /// The op removing the vertex
+        vertex: orswot::Op<V, A>,
+        /// The op removing its edges
+        edges: orswot::Op<(V, V), A>,
+    },
+}
+
+/// The ways merging two graphs may fail validation.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Validation<V, A> {
+    /// The vertex sets failed validation
+    Vertices(orswot::Validation<V, A>),
+    /// The edge sets failed validation
+    Edges(orswot::Validation<(V, V), A>),
+}
+
+impl<V: Debug, A: Debug> Display for Validation<V, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Validation::Vertices(e) => write!(f, "vertices: {}", e),
+            Validation::Edges(e) => write!(f, "edges: {}", e),
+        }
+    }
+}
+
+impl<V: Debug, A: Debug> std::error::Error for Validation<V, A> {}
+
+impl<V: Hash + Eq, A: Ord + Hash> Default for Graph<V, A> {
+    fn default() -> Self {
+        Graph {
+            vertices: Default::default(),
+            edges: Default::default(),
+        }
+    }
+}
+
+impl<V: Hash + Eq + Clone, A: Ord + Hash + Clone + Debug> CmRDT for Graph<V, A> {
+    type Op = Op<V, A>;
+    type Validation = DotRange<A>;
+
+    fn validate_op(&self, op: &Self::Op) -> Result<(), Self::Validation> {
+        // dots are drawn from the clock of the whole graph, so they are
+        // validated against it rather than against the set they land in
+        let dot = match op {
+            Op::Vertices(orswot::Op::Add { dot, .. }) | Op::Edges(orswot::Op::Add { dot, .. }) => {
+                dot
+            }
+            _ => return Ok(()),
+        };
+        self.clock().validate_op(dot)
+    }
+
+    fn apply(&mut self, op: Self::Op) {
+        match op {
+            Op::Vertices(op) => self.vertices.apply(op),
+            Op::Edges(op) => self.edges.apply(op),
+            Op::RmVertex { vertex, edges } => {
+                self.vertices.apply(vertex);
+                self.edges.apply(edges);
+            }
+        }
+    }
+}
+
+impl<V: Hash + Eq + Clone + Debug, A: Ord + Hash + Clone + Debug> CvRDT for Graph<V, A> {
+    type Validation = Validation<V, A>;
+
+    fn validate_merge(&self, other: &Self) -> Result<(), Self::Validation> {
+        self.vertices
+            .validate_merge(&other.vertices)
+            .map_err(Validation::Vertices)?;
+        self.edges
+            .validate_merge(&other.edges)
+            .map_err(Validation::Edges)
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.vertices.merge(other.vertices);
+        self.edges.merge(other.edges);
+    }
+}
+
+impl<V: Hash + Eq + Clone, A: Ord + Hash + Clone + Debug> Graph<V, A> {
+    /// Returns a new empty `Graph`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Return the clock of the whole graph, covering both vertex and edge
+    /// edits.
+    pub fn clock(&self) -> VClock<A> {
+        let mut clock = self.vertices.clock();
+        clock.merge(self.edges.clock());
+        clock
+    }
+
+    /// Add a vertex to the graph.
+    pub fn add_vertex(&self, vertex: V, ctx: AddCtx<A>) -> Op<V, A> {
+        Op::Vertices(self.vertices.add(vertex, ctx))
+    }
+
+    /// Remove a vertex from the graph, along with the edges to and from it
+    /// that this graph has seen.
+    ///
+    /// Edges to the vertex added concurrently with its removal are not
+    /// removed, they are hidden from reads until the vertex is added back.
+    pub fn remove_vertex(&self, vertex: V, ctx: RmCtx<A>) -> Op<V, A> {
+        let mut clock = VClock::new();
+        let mut incident = Vec::new();
+        for edge in self.edges.iter() {
+            let (from, to) = edge.val;
+            if *from == vertex || *to == vertex {
+                clock.merge(edge.rm_clock);
+                incident.push((from.clone(), to.clone()));
+            }
+        }
+        Op::RmVertex {
+            vertex: self.vertices.rm(vertex, ctx),
+            edges: self.edges.rm_all(incident, RmCtx { clock }),
+        }
+    }
+
+    /// Add an edge going from one vertex to another.
+    ///
+    /// The vertices don't need to be present, the edge is hidden from reads
+    /// while either of them is missing.
+    pub fn add_edge(&self, from: V, to: V, ctx: AddCtx<A>) -> Op<V, A> {
+        Op::Edges(self.edges.add((from, to), ctx))
+    }
+
+    /// Remove the edge going from one vertex to another.
+    pub fn remove_edge(&self, from: V, to: V, ctx: RmCtx<A>) -> Op<V, A> {
+        Op::Edges(self.edges.rm((from, to), ctx))
+    }
+
+    /// Check if a vertex is in the graph.
+    pub fn contains_vertex(&self, vertex: &V) -> ReadCtx<bool, A> {
+        ReadCtx {
+            add_clock: self.clock(),
+            ..self.vertices.contains(vertex)
+        }
+    }
+
+    /// Check if the edge going from one vertex to another is in the graph,
+    /// which requires both vertices to be in it too.
+    pub fn contains_edge(&self, from: &V, to: &V) -> ReadCtx<bool, A> {
+        let edge = self.edges.contains(&(from.clone(), to.clone()));
+        let val = edge.val && self.has_vertex(from) && self.has_vertex(to);
+        ReadCtx {
+            add_clock: self.clock(),
+            rm_clock: edge.rm_clock,
+            val,
+        }
+    }
+
+    /// Retrieve the current vertices.
+    pub fn vertices(&self) -> ReadCtx<HashSet<V>, A> {
+        ReadCtx {
+            add_clock: self.clock(),
+            rm_clock: self.vertices.clock(),
+            val: self.vertices.read().val,
+        }
+    }
+
+    /// Retrieve the current edges, leaving out those with a missing
+    /// endpoint.
+    pub fn edges(&self) -> ReadCtx<HashSet<(V, V)>, A> {
+        let val = self
+            .edges
+            .iter()
+            .map(|edge| edge.val)
+            .filter(|(from, to)| self.has_vertex(from) && self.has_vertex(to))
+            .cloned()
+            .collect();
+        ReadCtx {
+            add_clock: self.clock(),
+            rm_clock: self.edges.clock(),
+            val,
+        }
+    }
+
+    /// Retrieve the current read context
+    pub fn read_ctx(&self) -> ReadCtx<(), A> {
+        let clock = self.clock();
+        ReadCtx {
+            add_clock: clock.clone(),
+            rm_clock: clock,
+            val: (),
+        }
+    }
+
+    fn has_vertex(&self, vertex: &V) -> bool {
+        self.vertices.entries.contains_key(vertex)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_edge_to_concurrently_removed_vertex_is_hidden() {
+        let mut a = Graph::new();
+        a.apply(a.add_vertex(1, a.read_ctx().derive_add_ctx("A")));
+        a.apply(a.add_vertex(2, a.read_ctx().derive_add_ctx("A")));
+        let mut b = a.clone();
+
+        a.apply(a.remove_vertex(2, a.contains_vertex(&2).derive_rm_ctx()));
+        b.apply(b.add_edge(1, 2, b.read_ctx().derive_add_ctx("B")));
+        assert!(b.contains_edge(&1, &2).val);
+
+        a.merge(b.clone());
+        b.merge(a.clone());
+        assert_eq!(a, b);
+        assert_eq!(a.vertices().val, HashSet::from([1]));
+        assert!(a.edges().val.is_empty());
+        assert!(!a.contains_edge(&1, &2).val);
+
+        // the add-biased edge survived and shows again with its endpoint
+        a.apply(a.add_vertex(2, a.read_ctx().derive_add_ctx("A")));
+        assert_eq!(a.edges().val, HashSet::from([(1, 2)]));
+    }
+
+    #[test]
+    fn test_remove_vertex_removes_seen_edges() {
+        let mut g = Graph::new();
+        for v in [1, 2, 3] {
+            g.apply(g.add_vertex(v, g.read_ctx().derive_add_ctx("A")));
+        }
+        g.apply(g.add_edge(1, 2, g.read_ctx().derive_add_ctx("A")));
+        g.apply(g.add_edge(3, 1, g.read_ctx().derive_add_ctx("A")));
+        g.apply(g.add_edge(2, 3, g.read_ctx().derive_add_ctx("A")));
+
+        g.apply(g.remove_vertex(1, g.contains_vertex(&1).derive_rm_ctx()));
+        g.apply(g.add_vertex(1, g.read_ctx().derive_add_ctx("A")));
+        assert_eq!(g.edges().val, HashSet::from([(2, 3)]));
+
+        g.apply(g.remove_edge(2, 3, g.contains_edge(&2, &3).derive_rm_ctx()));
+        assert!(g.edges().val.is_empty());
+        assert_eq!(g.vertices().val, HashSet::from([1, 2, 3]));
+    }
+
+    #[test]
+    fn test_validate_op_uses_graph_clock() {
+        let mut g = Graph::new();
+        g.apply(g.add_vertex(1, g.read_ctx().derive_add_ctx("A")));
+        let op = g.add_edge(1, 1, g.read_ctx().derive_add_ctx("A"));
+        assert_eq!(g.validate_op(&op), Ok(()));
+    }
+}
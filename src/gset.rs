@@ -12,6 +12,14 @@ pub struct GSet<T: Ord> {
     value: BTreeSet<T>,
 }
 
+/// Renders the members as a JSON array in order.
+#[cfg(feature = "json")]
+impl<T: Ord + Serialize> crate::json::ToValue for GSet<T> {
+    fn to_value(&self) -> crate::json::Value {
+        crate::json::Value::Array(self.value.iter().map(crate::json::plain).collect())
+    }
+}
+
 impl<T: Ord> Default for GSet<T> {
     fn default() -> Self {
         GSet::new()
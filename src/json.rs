@@ -0,0 +1,38 @@
+//! Renders the logical value of a CRDT as a `serde_json::Value`.
+//!
+//! This is a view for humans and tooling: clocks, dots and removed data are
+//! left out, so it can't be turned back into a CRDT. Use the serde impls to
+//! persist or replicate CRDT state.
+//!
+//! ```rust
+//! use crdts::json::ToValue;
+//! use crdts::{CmRDT, MVReg, Map};
+//!
+//! let mut map: Map<&str, MVReg<u8, &str>, &str> = Map::new();
+//! let add_ctx = map.read_ctx().derive_add_ctx("A");
+//! map.apply(map.update("x", add_ctx, |reg, ctx| reg.write(7, ctx)));
+//!
+//! assert_eq!(map.to_value(), serde_json::json!({ "x": [7] }));
+//! ```
+use serde::Serialize;
+pub use serde_json::Value;
+
+/// CRDT's that can render their current value as JSON.
+pub trait ToValue {
+    /// Returns the current value of this CRDT as JSON.
+    fn to_value(&self) -> Value;
+}
+
+/// Renders a plain value, values that JSON can't represent (e.g. maps with
+/// non-string keys) are rendered as `null`.
+pub(crate) fn plain<T: Serialize + ?Sized>(val: &T) -> Value {
+    serde_json::to_value(val).unwrap_or(Value::Null)
+}
+
+/// Renders the members of an unordered collection as an array sorted by their
+/// JSON text, so every replica renders the same view.
+pub(crate) fn sorted_array<'a, T: Serialize + 'a>(members: impl Iterator<Item = &'a T>) -> Value {
+    let mut members: Vec<Value> = members.map(plain).collect();
+    members.sort_by_cached_key(|member| member.to_string());
+    Value::Array(members)
+}
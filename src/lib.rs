@@ -12,11 +12,15 @@
 #![deny(unreachable_pub)]
 
 mod traits;
-pub use crate::traits::{Actor, CmRDT, CvRDT, ResetRemove};
+pub use crate::traits::{
+    Actor, CausalContext, CmRDT, Concurrency, CvRDT, DotStore, HeapSize, LogicalEq, ResetRemove,
+};
 
 /// This module contains a Last-Write-Wins Register.
 pub mod lwwreg;
 
+pub mod lwwmap;
+
 /// This module contains a Multi-Value Register.
 pub mod mvreg;
 
@@ -52,6 +56,9 @@ pub mod gcounter;
 /// This module contains a Grow-only Set.
 pub mod gset;
 
+/// This module contains a directed Graph built on `Orswot`s.
+pub mod graph;
+
 /// This module contains a Grow-only List.
 #[cfg(feature = "num")]
 pub mod glist;
@@ -60,12 +67,25 @@ pub mod glist;
 #[cfg(feature = "num")]
 pub mod pncounter;
 
+/// This module contains a Positive-Negative counted Set.
+#[cfg(feature = "num")]
+pub mod pnset;
+
 /// This module contains a Map with Reset-Remove and Observed-Remove semantics.
 pub mod map;
 
+pub mod structreg;
+
 /// This module contains context for editing a CRDT.
 pub mod ctx;
 
+/// This module re-exports the commonly used types and traits.
+pub mod prelude;
+
+/// This module contains a read only JSON view of CRDT values.
+#[cfg(feature = "json")]
+pub mod json;
+
 /// This module contains a Sequence.
 #[cfg(feature = "num")]
 pub mod list;
@@ -75,7 +95,7 @@ mod serde_helper;
 #[cfg(feature = "num")]
 pub use {
     gcounter::GCounter, glist::GList, identifier::Identifier, list::List, maxreg::MaxReg,
-    minreg::MinReg, pncounter::PNCounter,
+    minreg::MinReg, pncounter::PNCounter, pnset::PNSet,
 };
 
 // /// Version Vector with Exceptions
@@ -83,8 +103,8 @@ pub use {
 
 /// Top-level re-exports for CRDT structures.
 pub use crate::{
-    dot::Dot, dot::DotRange, dot::OrdDot, gset::GSet, lwwreg::LWWReg, map::Map, mvreg::MVReg,
-    orswot::Orswot, vclock::VClock,
+    dot::Dot, dot::DotRange, dot::OrdDot, graph::Graph, gset::GSet, lwwmap::LWWMap, lwwreg::LWWReg,
+    map::Map, mvreg::MVReg, orswot::Orswot, structreg::StructReg, vclock::VClock,
 };
 
 /// A re-export of the quickcheck crate for external property tests
@@ -0,0 +1,274 @@
+//! # LWWMap - A key/value store where the latest write to a key wins
+//!
+//! `LWWMap` is a thin layer over `Map<K, Register<V, M, A>, A>` that hides
+//! the register wrapping. Removes keep the reset-remove semantics of `Map`,
+//! concurrent writes to the same key are resolved by their markers.
+
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ctx::{AddCtx, ReadCtx, RmCtx};
+use crate::lwwreg::{self, LWWReg};
+use crate::map::{self, Map, Val};
+use crate::{CmRDT, CvRDT, Dot, LogicalEq, ResetRemove, VClock};
+
+/// A `Map` of last-write-wins registers.
+///
+/// As with `LWWReg`, markers must be globally unique and grow monotonically.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LWWMap<K: Ord, V, M, A: Ord + Hash>
+where
+    Register<V, M, A>: Val<A>,
+{
+    map: Map<K, Register<V, M, A>, A>,
+}
+
+/// Operations which can be applied to an `LWWMap`.
+pub type Op<K, V, M, A> = map::Op<K, Register<V, M, A>, A>;
+
+/// The last-write-wins register held under each key of an `LWWMap`.
+///
+/// A plain `LWWReg` can't be reset-removed: once a write loses to one with a
+/// greater marker its value is gone, so a remove of the winner can't bring
+/// it back, and replicas that see the remove and the losing write in
+/// different orders diverge. The register instead keeps each write along
+/// with its dot and the clock it was made under, and drops a write only
+/// once a remove covers its dot, or a write that has seen it carries a
+/// greater marker. Reads return the write with the greatest marker.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Register<V, M, A: Ord> {
+    // sorted by dot so that equal registers compare equal
+    writes: Vec<Write<V, M, A>>,
+}
+
+/// A write to a `Register`, it is both the op and what the register keeps.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Write<V, M, A: Ord> {
+    /// The dot of the write, a remove covering it drops the write
+    pub dot: Dot<A>,
+    /// The clock the write was made under
+    pub seen: VClock<A>,
+    /// The value and its marker
+    pub reg: LWWReg<V, M>,
+}
+
+impl<V, M: Ord, A: Ord> Write<V, M, A> {
+    /// True if the other write has seen this one and has a greater marker,
+    /// making this write obsolete.
+    fn replaced_by(&self, other: &Self) -> bool {
+        other.seen.get(&self.dot.actor) >= self.dot.counter && self.reg.marker < other.reg.marker
+    }
+}
+
+impl<V, M, A: Ord> Default for Register<V, M, A> {
+    fn default() -> Self {
+        Self { writes: Vec::new() }
+    }
+}
+
+impl<V: PartialEq, M: Ord, A: Ord> Register<V, M, A> {
+    /// Build the op that writes `val` witnessed by `marker`.
+    pub fn write(&self, val: V, marker: M, ctx: AddCtx<A>) -> Write<V, M, A> {
+        Write {
+            dot: ctx.dot,
+            seen: ctx.clock,
+            reg: LWWReg { val, marker },
+        }
+    }
+
+    /// The write with the greatest marker, `None` if the register is empty.
+    pub fn read(&self) -> Option<&LWWReg<V, M>> {
+        self.writes
+            .iter()
+            .map(|write| &write.reg)
+            .max_by(|a, b| a.marker.cmp(&b.marker))
+    }
+
+    fn insert(&mut self, write: Write<V, M, A>) {
+        let dot = (&write.dot.actor, write.dot.counter);
+        let found = self
+            .writes
+            .binary_search_by(|w| (&w.dot.actor, w.dot.counter).cmp(&dot));
+        if let Err(idx) = found {
+            if !self.writes.iter().any(|w| write.replaced_by(w)) {
+                self.writes.insert(idx, write);
+            }
+        }
+    }
+
+    fn drop_replaced(&mut self) {
+        let replaced: Vec<bool> = self
+            .writes
+            .iter()
+            .map(|write| self.writes.iter().any(|other| write.replaced_by(other)))
+            .collect();
+        let mut replaced = replaced.into_iter();
+        self.writes.retain(|_| !replaced.next().unwrap_or(false));
+    }
+
+    fn validate_write(&self, write: &Write<V, M, A>) -> Result<(), lwwreg::Validation> {
+        self.writes
+            .iter()
+            .try_for_each(|w| w.reg.validate_update(&write.reg.val, &write.reg.marker))
+    }
+}
+
+impl<V: Clone + PartialEq, M: Clone + Ord, A: Ord + Clone> CmRDT for Register<V, M, A> {
+    type Op = Write<V, M, A>;
+    type Validation = lwwreg::Validation;
+
+    fn validate_op(&self, op: &Self::Op) -> Result<(), Self::Validation> {
+        self.validate_write(op)
+    }
+
+    fn apply(&mut self, op: Self::Op) {
+        self.insert(op);
+        self.drop_replaced();
+    }
+}
+
+impl<V: Clone + PartialEq, M: Clone + Ord, A: Ord + Clone> CvRDT for Register<V, M, A> {
+    type Validation = lwwreg::Validation;
+
+    fn validate_merge(&self, other: &Self) -> Result<(), Self::Validation> {
+        other
+            .writes
+            .iter()
+            .try_for_each(|write| self.validate_write(write))
+    }
+
+    fn merge(&mut self, other: Self) {
+        for write in other.writes {
+            self.insert(write);
+        }
+        self.drop_replaced();
+    }
+}
+
+/// Renders the value of the winning write, or null for an empty register.
+#[cfg(feature = "json")]
+impl<V: Serialize + PartialEq, M: Ord, A: Ord> crate::json::ToValue for Register<V, M, A> {
+    fn to_value(&self) -> crate::json::Value {
+        self.read()
+            .map(|reg| crate::json::plain(&reg.val))
+            .unwrap_or(crate::json::Value::Null)
+    }
+}
+
+impl<V: PartialEq, M: Ord, A: Ord> LogicalEq for Register<V, M, A> {
+    /// Returns true if both registers read the same value, ignoring the
+    /// writes it lost to.
+    fn logically_eq(&self, other: &Self) -> bool {
+        self.read().map(|reg| &reg.val) == other.read().map(|reg| &reg.val)
+    }
+}
+
+impl<V, M, A: Ord> ResetRemove<A> for Register<V, M, A> {
+    fn reset_remove(&mut self, clock: &VClock<A>) {
+        self.writes
+            .retain(|write| clock.get(&write.dot.actor) < write.dot.counter);
+    }
+}
+
+impl<K: Ord, V, M, A: Ord + Hash> Default for LWWMap<K, V, M, A>
+where
+    Register<V, M, A>: Val<A>,
+{
+    fn default() -> Self {
+        Self {
+            map: Map::default(),
+        }
+    }
+}
+
+impl<K, V, M, A> CmRDT for LWWMap<K, V, M, A>
+where
+    K: Ord + Clone,
+    V: Clone + PartialEq + Debug,
+    M: Clone + Ord + Debug,
+    A: Ord + Hash + Clone + Debug,
+{
+    type Op = Op<K, V, M, A>;
+    type Validation = <Map<K, Register<V, M, A>, A> as CmRDT>::Validation;
+
+    fn validate_op(&self, op: &Self::Op) -> Result<(), Self::Validation> {
+        self.map.validate_op(op)
+    }
+
+    fn apply(&mut self, op: Self::Op) {
+        self.map.apply(op)
+    }
+}
+
+impl<K, V, M, A> CvRDT for LWWMap<K, V, M, A>
+where
+    K: Ord + Clone + Debug,
+    V: Clone + PartialEq + Debug,
+    M: Clone + Ord + Debug,
+    A: Ord + Hash + Clone + Debug,
+{
+    type Validation = <Map<K, Register<V, M, A>, A> as CvRDT>::Validation;
+
+    fn validate_merge(&self, other: &Self) -> Result<(), Self::Validation> {
+        self.map.validate_merge(&other.map)
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.map.merge(other.map)
+    }
+}
+
+impl<K, V, M, A> LWWMap<K, V, M, A>
+where
+    K: Ord,
+    V: Clone + PartialEq,
+    M: Clone + Ord,
+    A: Ord + Hash + Clone,
+{
+    /// Constructs an empty LWWMap
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Generate an Op to set the value of a key, witnessed by `marker`.
+    pub fn set(&self, key: impl Into<K>, val: V, marker: M, ctx: AddCtx<A>) -> Op<K, V, M, A> {
+        self.map
+            .update(key, ctx, |reg, ctx| reg.write(val, marker, ctx))
+    }
+
+    /// Returns the current value of a key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.map
+            .val(key)
+            .and_then(Register::read)
+            .map(|reg| &reg.val)
+    }
+
+    /// Generate an Op to remove a key.
+    pub fn rm(&self, key: impl Into<K>, ctx: RmCtx<A>) -> Op<K, V, M, A> {
+        self.map.rm(key, ctx)
+    }
+
+    /// Retrieve the current read context
+    pub fn read_ctx(&self) -> ReadCtx<(), A> {
+        self.map.read_ctx()
+    }
+
+    /// Returns the number of keys in the map
+    pub fn len(&self) -> ReadCtx<usize, A> {
+        self.map.len()
+    }
+
+    /// Returns true if the map has no keys, false otherwise
+    pub fn is_empty(&self) -> ReadCtx<bool, A> {
+        self.map.is_empty()
+    }
+
+    /// Borrow the underlying `Map` of registers.
+    pub fn as_map(&self) -> &Map<K, Register<V, M, A>, A> {
+        &self.map
+    }
+}
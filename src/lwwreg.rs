@@ -2,7 +2,7 @@ use std::{error, fmt};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{CmRDT, CvRDT};
+use crate::{CmRDT, Concurrency, CvRDT};
 
 /// `LWWReg` is a simple CRDT that contains an arbitrary value
 /// along with an `Ord` that tracks causality. It is the responsibility
@@ -91,6 +91,16 @@ impl<V: PartialEq, M: Ord> CmRDT for LWWReg<V, M> {
     }
 }
 
+impl<V, M> Concurrency for LWWReg<V, M> {}
+
+/// Renders the current value, the marker is left out.
+#[cfg(feature = "json")]
+impl<V: Serialize, M> crate::json::ToValue for LWWReg<V, M> {
+    fn to_value(&self) -> crate::json::Value {
+        crate::json::plain(&self.val)
+    }
+}
+
 impl<V: PartialEq, M: Ord> LWWReg<V, M> {
     /// Construct a new LwwReg initialized with the given value and marker
     pub fn new(val: V, marker: M) -> Self {
@@ -1,14 +1,24 @@
 use std::cmp::Ordering;
-use std::collections::HashMap;
 use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{HashMap, VecDeque};
 use std::fmt::{self, Debug, Display};
 use std::hash::Hash;
 use std::mem;
+use std::ops::{Deref, Index, RangeBounds};
 
 use serde::{Deserialize, Serialize};
 
-use crate::ctx::{AddCtx, ReadCtx, RmCtx};
-use crate::{CmRDT, CvRDT, Dot, ResetRemove, VClock};
+use crate::ctx::{AddCtx, ReadCtx, RmCtx, WriteCtx};
+use crate::lwwmap::Register;
+use crate::{
+    CausalContext, CmRDT, Concurrency, CvRDT, Dot, DotStore, HeapSize, LogicalEq, MVReg, Orswot,
+    ResetRemove, VClock,
+};
+
+#[cfg(feature = "num")]
+use crate::GCounter;
+#[cfg(feature = "num")]
+use num::BigUint;
 
 /// Val Trait alias to reduce redundancy in type decl.
 pub trait Val<A: Ord>: Clone + Default + ResetRemove<A> + CmRDT {}
@@ -20,6 +30,142 @@ where
 {
 }
 
+/// CRDT's that a path of map keys can pass through or end in, used by
+/// `Map::nested_put` to build the op for a deeply nested `MVReg`.
+pub trait NestedPut<K, T, A: Ord>: CmRDT {
+    /// Build the op that writes `val` at the end of `path`.
+    ///
+    /// Panics if the path does not match the depth of the nesting.
+    fn put_path(&self, path: &[K], val: T, ctx: AddCtx<A>) -> Self::Op;
+}
+
+impl<K, T, A: Ord + Clone + Debug> NestedPut<K, T, A> for MVReg<T, A> {
+    fn put_path(&self, path: &[K], val: T, ctx: AddCtx<A>) -> Self::Op {
+        assert!(path.is_empty(), "path is longer than the nesting of maps");
+        self.write(val, ctx)
+    }
+}
+
+impl<K, V, T, A> NestedPut<K, T, A> for Map<K, V, A>
+where
+    K: Ord + Clone,
+    V: Val<A> + Debug + NestedPut<K, T, A>,
+    A: Ord + Hash + Clone + Debug,
+{
+    fn put_path(&self, path: &[K], val: T, ctx: AddCtx<A>) -> Self::Op {
+        let (key, rest) = path
+            .split_first()
+            .expect("path is shorter than the nesting of maps");
+        self.update(key.clone(), ctx, |inner, ctx| {
+            inner.put_path(rest, val, ctx)
+        })
+    }
+}
+
+/// CRDT's that a path of map keys can pass through or end in, used by
+/// `Map::update_path` to build the op for a deeply nested leaf `L`.
+pub trait NestedUpdate<K, L: CmRDT, A: Ord>: CmRDT {
+    /// Build the op that applies `f` to the leaf at the end of `path`,
+    /// where `depth` is the position of `path[0]` in the full path.
+    ///
+    /// Panics if the path does not match the depth of the nesting.
+    fn update_at<F>(
+        &self,
+        path: &[K],
+        depth: usize,
+        ctx: AddCtx<A>,
+        f: F,
+    ) -> Result<Self::Op, PathError>
+    where
+        F: FnOnce(&L, AddCtx<A>) -> L::Op;
+}
+
+impl<K, T, A: Ord + Clone + Debug> NestedUpdate<K, Self, A> for MVReg<T, A> {
+    fn update_at<F>(
+        &self,
+        path: &[K],
+        _: usize,
+        ctx: AddCtx<A>,
+        f: F,
+    ) -> Result<Self::Op, PathError>
+    where
+        F: FnOnce(&Self, AddCtx<A>) -> Self::Op,
+    {
+        assert!(path.is_empty(), "path is longer than the nesting of maps");
+        Ok(f(self, ctx))
+    }
+}
+
+impl<K, M: Hash + Clone + Eq, A: Ord + Hash + Clone + Debug> NestedUpdate<K, Self, A>
+    for Orswot<M, A>
+{
+    fn update_at<F>(
+        &self,
+        path: &[K],
+        _: usize,
+        ctx: AddCtx<A>,
+        f: F,
+    ) -> Result<Self::Op, PathError>
+    where
+        F: FnOnce(&Self, AddCtx<A>) -> Self::Op,
+    {
+        assert!(path.is_empty(), "path is longer than the nesting of maps");
+        Ok(f(self, ctx))
+    }
+}
+
+impl<K, V, L, A> NestedUpdate<K, L, A> for Map<K, V, A>
+where
+    K: Ord + Clone,
+    V: Val<A> + Debug + NestedUpdate<K, L, A>,
+    L: CmRDT,
+    A: Ord + Hash + Clone + Debug,
+{
+    fn update_at<F>(
+        &self,
+        path: &[K],
+        depth: usize,
+        ctx: AddCtx<A>,
+        f: F,
+    ) -> Result<Self::Op, PathError>
+    where
+        F: FnOnce(&L, AddCtx<A>) -> L::Op,
+    {
+        let (key, rest) = path
+            .split_first()
+            .expect("path is shorter than the nesting of maps");
+        let dot = ctx.dot.clone();
+        let op = match self.entries.get(key) {
+            Some(entry) => entry.val.update_at(rest, depth + 1, ctx, f)?,
+            // only the leaf may be created by the update
+            None if rest.is_empty() => V::default().update_at(rest, depth + 1, ctx, f)?,
+            None => return Err(PathError::MissingLevel(depth)),
+        };
+        Ok(Op::Up {
+            dot,
+            key: key.clone(),
+            op,
+        })
+    }
+}
+
+/// Errors building a nested op, see `Map::update_path`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathError {
+    /// The key at this position of the path is missing from its map.
+    MissingLevel(usize),
+}
+
+impl Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::MissingLevel(depth) => write!(f, "no entry at level {} of the path", depth),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
 /// Map CRDT - Supports Composition of CRDT's with reset-remove semantics.
 ///
 /// Reset-remove means that if one replica removes an entry while another
@@ -29,13 +175,114 @@ where
 ///
 /// See examples/reset_remove.rs for an example of reset-remove semantics
 /// in action.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Map<K: Ord, V: Val<A>, A: Ord + Hash> {
     // This clock stores the current version of the Map, it should
     // be greator or equal to all Entry.clock's in the Map.
     clock: VClock<A>,
     entries: BTreeMap<K, Entry<V, A>>,
     deferred: HashMap<VClock<A>, BTreeSet<K>>,
+
+    // Local audit metadata, it plays no part in merges or equality and is
+    // never serialized.
+    #[serde(skip, default = "Option::default")]
+    tombstones: Option<TombstoneLog<K, A>>,
+    #[serde(skip, default = "Option::default")]
+    deferred_limit: Option<DeferredLimit<A>>,
+    #[serde(skip, default = "Option::default")]
+    capacity: Option<CapacityPolicy<K>>,
+}
+
+/// How a capacity bounded Map picks the entries to evict, see
+/// `Map::with_capacity_policy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EvictionPolicy {
+    /// Evict the entry that was updated least recently.
+    LeastRecentlyUpdated,
+    /// Evict the entry that was created first, updates don't refresh an entry.
+    FirstCreated,
+}
+
+/// A bounded log of the keys dropped by removes, see `Map::with_tombstone_log`.
+#[derive(Debug, Clone)]
+struct TombstoneLog<K, A: Ord> {
+    capacity: usize,
+    removed: VecDeque<(K, VClock<A>)>,
+}
+
+/// Bounds the number of deferred removes, see `Map::with_deferred_limit`.
+#[derive(Debug, Clone)]
+struct DeferredLimit<A: Ord> {
+    limit: usize,
+    // the clocks of the deferred removes, oldest first
+    order: VecDeque<VClock<A>>,
+    evicted: usize,
+}
+
+/// Tracks entry recency to bound the size of a Map, see `Map::with_capacity_policy`.
+#[derive(Debug, Clone)]
+struct CapacityPolicy<K> {
+    capacity: usize,
+    policy: EvictionPolicy,
+    tick: u64,
+    // the last tick of each key and the keys by tick, oldest first. Keys
+    // that have left the map are only forgotten when they reach the front.
+    ticks: BTreeMap<K, u64>,
+    order: BTreeMap<u64, K>,
+}
+
+impl<K: Ord + Clone> CapacityPolicy<K> {
+    /// Record an update to `key`, `created` tells us if the update created the entry.
+    fn touch(&mut self, key: &K, created: bool) {
+        if !created && self.policy == EvictionPolicy::FirstCreated {
+            return;
+        }
+        self.tick += 1;
+        if let Some(old_tick) = self.ticks.insert(key.clone(), self.tick) {
+            self.order.remove(&old_tick);
+        }
+        self.order.insert(self.tick, key.clone());
+    }
+
+    /// Drop the oldest entries until we are within capacity.
+    fn evict<V: Val<A>, A: Ord>(&mut self, entries: &mut BTreeMap<K, Entry<V, A>>) {
+        // entries that arrived through a merge haven't been touched yet
+        if self.ticks.len() < entries.len() {
+            for key in entries.keys() {
+                if !self.ticks.contains_key(key) {
+                    self.touch(key, true);
+                }
+            }
+        }
+
+        while entries.len() > self.capacity {
+            match self.order.pop_first() {
+                Some((_, key)) => {
+                    self.ticks.remove(&key);
+                    entries.remove(&key);
+                }
+                None => break,
+            }
+        }
+
+        // forget keys that were removed from the map by other means
+        if self.ticks.len() > 2 * self.capacity {
+            self.ticks.retain(|key, _| entries.contains_key(key));
+            self.order.retain(|_, key| entries.contains_key(key));
+        }
+    }
+}
+
+impl<K, A: Ord> TombstoneLog<K, A> {
+    fn record(&mut self, key: K, clock: VClock<A>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.removed.len() == self.capacity {
+            self.removed.pop_front();
+        }
+        self.removed.push_back((key, clock));
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -47,8 +294,14 @@ struct Entry<V: Val<A>, A: Ord> {
     val: V,
 }
 
+/// How many entries `Map::merge_with_progress` processes between callbacks.
+const MERGE_PROGRESS_INTERVAL: usize = 4096;
+
 /// Operations which can be applied to the Map CRDT
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// The alternate `Debug` format (`{:#?}`) renders nested ops as an indented
+/// tree, see `Op::pretty`.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Op<K: Ord, V: Val<A>, A: Ord> {
     /// Remove a key from the map
     Rm {
@@ -66,6 +319,366 @@ pub enum Op<K: Ord, V: Val<A>, A: Ord> {
         /// The operation to apply on the value under `key`
         op: V::Op,
     },
+    /// Update several entries at once, see `Map::update_many`
+    ///
+    /// The n-th update is made under the n-th dot counting up from `dot`, so
+    /// each entry still gets a dot of its own, but the updates are only ever
+    /// applied together.
+    UpMany {
+        /// Actors version at the time of the first update
+        dot: Dot<A>,
+        /// Keys of the values to update along with the operation to apply
+        updates: Vec<(K, V::Op)>,
+    },
+}
+
+impl<K: Ord + Debug, V: Val<A>, A: Ord + Debug> Debug for Op<K, V, A>
+where
+    V::Op: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            return match self {
+                Op::Rm { clock, keyset } => f
+                    .debug_struct("Rm")
+                    .field("clock", clock)
+                    .field("keyset", keyset)
+                    .finish(),
+                Op::Up { dot, key, op } => f
+                    .debug_struct("Up")
+                    .field("dot", dot)
+                    .field("key", key)
+                    .field("op", op)
+                    .finish(),
+                Op::UpMany { dot, updates } => f
+                    .debug_struct("UpMany")
+                    .field("dot", dot)
+                    .field("updates", updates)
+                    .finish(),
+            };
+        }
+
+        // one line per level, nested ops indented under the key they update
+        let nested = |f: &mut fmt::Formatter<'_>, op: &V::Op, indent: &str| {
+            for line in format!("{:#?}", op).lines() {
+                write!(f, "\n{}{}", indent, line)?;
+            }
+            Ok(())
+        };
+        match self {
+            Op::Rm { clock, keyset } => write!(f, "Rm {:?} {:?}", clock, keyset),
+            Op::Up { dot, key, op } => {
+                write!(f, "Up {:?} {:?}", dot, key)?;
+                nested(f, op, "  ")
+            }
+            Op::UpMany { dot, updates } => {
+                write!(f, "UpMany {:?}", dot)?;
+                for (key, op) in updates {
+                    write!(f, "\n  {:?}", key)?;
+                    nested(f, op, "    ")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<K: Ord + Debug, V: Val<A>, A: Ord + Debug> Op<K, V, A>
+where
+    V::Op: Debug,
+{
+    /// Render the op as an indented tree, one level of nesting per line.
+    ///
+    /// Each update shows its dot and key, with the op on the value nested
+    /// below it. This is the alternate `Debug` format and is meant for
+    /// reading failing tests, not for parsing.
+    ///
+    /// ```rust
+    /// use crdts::{Map, Orswot};
+    ///
+    /// let map: Map<u8, Map<u8, Orswot<u8, u8>, u8>, u8> = Map::new();
+    /// let ctx = map.read_ctx().derive_add_ctx(1);
+    /// let op = map.update(2, ctx, |inner, ctx| {
+    ///     inner.update(3, ctx, |set, ctx| set.add(4, ctx))
+    /// });
+    /// assert_eq!(op.pretty(), "Up 1.1 2\n  Up 1.1 3\n    Add(1.1, [4])");
+    /// ```
+    pub fn pretty(&self) -> String {
+        format!("{:#?}", self)
+    }
+}
+
+impl<K: Ord, V: Val<A>, A: Ord + Clone> Op<K, V, A> {
+    /// The (dot, key) pairs this op updates, empty for removes.
+    fn updated_dots(&self) -> Vec<(Dot<A>, &K)> {
+        match self {
+            Op::Rm { .. } => Vec::new(),
+            Op::Up { dot, key, .. } => vec![(dot.clone(), key)],
+            Op::UpMany { dot, updates } => (dot.counter..)
+                .zip(updates.iter())
+                .map(|(counter, (key, _))| (Dot::new(dot.actor.clone(), counter), key))
+                .collect(),
+        }
+    }
+}
+
+/// The state a peer needs to catch up with a Map, see `Map::sync_payload`.
+///
+/// Only the replicated state is shipped, the local settings of the map
+/// (e.g. its capacity or tombstone log) stay behind.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncPayload<K: Ord, V: Val<A>, A: Ord + Hash> {
+    clock: VClock<A>,
+    entries: BTreeMap<K, Entry<V, A>>,
+    deferred: HashMap<VClock<A>, BTreeSet<K>>,
+}
+
+/// How often each decision was taken while merging two maps, see
+/// `Map::merge_with_stats`.
+///
+/// Every entry held by either map is counted once under `added`, `kept`,
+/// `dropped`, `merged` or `unchanged`. Lots of drops of entries the other
+/// map still held point to frequent removes concurrent with updates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeStats {
+    /// Entries only the other map held, in versions this map had not seen.
+    pub added: usize,
+    /// Entries only this map held, in versions the other map had not seen.
+    pub kept: usize,
+    /// Entries dropped because a map had seen them and removed them.
+    pub dropped: usize,
+    /// Entries both maps held in different versions, merged into one.
+    pub merged: usize,
+    /// Entries both maps held in the same version.
+    pub unchanged: usize,
+    /// Deferred removes taken over from the other map.
+    pub deferred: usize,
+}
+
+/// A batch of ops that serializes the actor of its updates once.
+///
+/// Ops are usually shipped in runs from a single actor. In a batch, updates
+/// from the actor of the first update are encoded with their counters only,
+/// every other op is encoded as is. Deserializing restores the exact ops.
+pub struct OpBatch<K: Ord, V: Val<A>, A: Ord> {
+    ops: Vec<Op<K, V, A>>,
+}
+
+impl<K: Ord, V: Val<A>, A: Ord> Debug for OpBatch<K, V, A>
+where
+    Op<K, V, A>: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpBatch").field("ops", &self.ops).finish()
+    }
+}
+
+impl<K: Ord, V: Val<A>, A: Ord> Clone for OpBatch<K, V, A>
+where
+    Op<K, V, A>: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            ops: self.ops.clone(),
+        }
+    }
+}
+
+impl<K: Ord, V: Val<A>, A: Ord> PartialEq for OpBatch<K, V, A>
+where
+    Op<K, V, A>: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.ops == other.ops
+    }
+}
+
+impl<K: Ord, V: Val<A>, A: Ord> Eq for OpBatch<K, V, A> where Op<K, V, A>: Eq {}
+
+#[derive(Serialize)]
+#[serde(bound = "K: Serialize, V::Op: Serialize, A: Serialize")]
+struct OpBatchRef<'a, K: Ord, V: Val<A>, A: Ord> {
+    actor: Option<&'a A>,
+    ops: Vec<BatchedOpRef<'a, K, V, A>>,
+}
+
+#[derive(Serialize)]
+#[serde(bound = "K: Serialize, V::Op: Serialize, A: Serialize")]
+enum BatchedOpRef<'a, K: Ord, V: Val<A>, A: Ord> {
+    Up {
+        counter: u64,
+        key: &'a K,
+        op: &'a V::Op,
+    },
+    UpMany {
+        counter: u64,
+        updates: &'a Vec<(K, V::Op)>,
+    },
+    Op(&'a Op<K, V, A>),
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "K: Deserialize<'de>, V::Op: Deserialize<'de>, A: Deserialize<'de>")]
+struct OpBatchOwned<K: Ord, V: Val<A>, A: Ord> {
+    actor: Option<A>,
+    ops: Vec<BatchedOp<K, V, A>>,
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "K: Deserialize<'de>, V::Op: Deserialize<'de>, A: Deserialize<'de>")]
+enum BatchedOp<K: Ord, V: Val<A>, A: Ord> {
+    Up {
+        counter: u64,
+        key: K,
+        op: V::Op,
+    },
+    UpMany {
+        counter: u64,
+        updates: Vec<(K, V::Op)>,
+    },
+    Op(Op<K, V, A>),
+}
+
+impl<K: Ord, V: Val<A>, A: Ord> OpBatch<K, V, A> {
+    /// The ops in this batch, in the order they were added.
+    pub fn ops(&self) -> &[Op<K, V, A>] {
+        &self.ops
+    }
+
+    /// Consumes the batch and returns its ops.
+    pub fn into_ops(self) -> Vec<Op<K, V, A>> {
+        self.ops
+    }
+}
+
+impl<K: Ord, V: Val<A>, A: Ord> From<Vec<Op<K, V, A>>> for OpBatch<K, V, A> {
+    fn from(ops: Vec<Op<K, V, A>>) -> Self {
+        Self { ops }
+    }
+}
+
+impl<K, V, A> Serialize for OpBatch<K, V, A>
+where
+    K: Ord + Serialize,
+    V: Val<A>,
+    V::Op: Serialize,
+    A: Ord + Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let actor = self.ops.iter().find_map(|op| match op {
+            Op::Up { dot, .. } | Op::UpMany { dot, .. } => Some(&dot.actor),
+            Op::Rm { .. } => None,
+        });
+        let ops = self
+            .ops
+            .iter()
+            .map(|op| match op {
+                Op::Up { dot, key, op } if Some(&dot.actor) == actor => BatchedOpRef::Up {
+                    counter: dot.counter,
+                    key,
+                    op,
+                },
+                Op::UpMany { dot, updates } if Some(&dot.actor) == actor => BatchedOpRef::UpMany {
+                    counter: dot.counter,
+                    updates,
+                },
+                op => BatchedOpRef::Op(op),
+            })
+            .collect();
+        OpBatchRef { actor, ops }.serialize(serializer)
+    }
+}
+
+impl<'de, K, V, A> Deserialize<'de> for OpBatch<K, V, A>
+where
+    K: Ord + Deserialize<'de>,
+    V: Val<A>,
+    V::Op: Deserialize<'de>,
+    A: Ord + Clone + Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let OpBatchOwned { actor, ops } = OpBatchOwned::<K, V, A>::deserialize(deserializer)?;
+        let dot = |counter| match &actor {
+            Some(actor) => Ok(Dot::new(actor.clone(), counter)),
+            None => Err(serde::de::Error::custom(
+                "batched update without a batch actor",
+            )),
+        };
+        let ops = ops
+            .into_iter()
+            .map(|op| {
+                Ok(match op {
+                    BatchedOp::Up { counter, key, op } => Op::Up {
+                        dot: dot(counter)?,
+                        key,
+                        op,
+                    },
+                    BatchedOp::UpMany { counter, updates } => Op::UpMany {
+                        dot: dot(counter)?,
+                        updates,
+                    },
+                    BatchedOp::Op(op) => op,
+                })
+            })
+            .collect::<Result<_, D::Error>>()?;
+        Ok(Self { ops })
+    }
+}
+
+/// A Map that records the ops it applies, see `Map::with_op_recorder`.
+///
+/// Reads go through to the map, while ops must be applied through the
+/// recorder's `CmRDT::apply`. Only ops that changed the map are recorded,
+/// so replaying the log onto an empty map rebuilds it.
+pub struct OpRecorder<K: Ord, V: Val<A>, A: Ord + Hash> {
+    map: Map<K, V, A>,
+    log: Vec<Op<K, V, A>>,
+}
+
+impl<K: Ord, V: Val<A>, A: Ord + Hash> OpRecorder<K, V, A> {
+    /// Returns the ops recorded so far, in the order they were applied.
+    pub fn log(&self) -> &[Op<K, V, A>] {
+        &self.log
+    }
+
+    /// Takes the ops recorded so far, e.g. to ship them, and starts a new
+    /// log.
+    pub fn take_log(&mut self) -> Vec<Op<K, V, A>> {
+        mem::take(&mut self.log)
+    }
+
+    /// Stops recording and returns the map, dropping the log.
+    pub fn into_map(self) -> Map<K, V, A> {
+        self.map
+    }
+}
+
+impl<K: Ord, V: Val<A>, A: Ord + Hash> Deref for OpRecorder<K, V, A> {
+    type Target = Map<K, V, A>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<K, V, A> CmRDT for OpRecorder<K, V, A>
+where
+    K: Ord + Clone,
+    V: Val<A> + Debug,
+    A: Ord + Hash + Clone + Debug,
+    Op<K, V, A>: Clone,
+{
+    type Op = Op<K, V, A>;
+    type Validation = <Map<K, V, A> as CmRDT>::Validation;
+
+    fn validate_op(&self, op: &Self::Op) -> Result<(), Self::Validation> {
+        self.map.validate_op(op)
+    }
+
+    fn apply(&mut self, op: Self::Op) {
+        let log = &mut self.log;
+        self.map.apply_observed(op, |op| log.push(op.clone()));
+    }
 }
 
 impl<V: Val<A>, A: Ord> Default for Entry<V, A> {
@@ -83,12 +696,72 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash> Default for Map<K, V, A> {
             clock: Default::default(),
             entries: Default::default(),
             deferred: Default::default(),
+            tombstones: None,
+            deferred_limit: None,
+            capacity: None,
         }
     }
 }
 
+impl<K: Ord, V: Val<A> + PartialEq, A: Ord + Hash> PartialEq for Map<K, V, A> {
+    fn eq(&self, other: &Self) -> bool {
+        // the tombstone log, deferred limit and capacity policy are local and
+        // don't take part in equality
+        self.clock == other.clock
+            && self.entries == other.entries
+            && self.deferred == other.deferred
+    }
+}
+
+impl<K: Ord, V: Val<A> + Eq, A: Ord + Hash> Eq for Map<K, V, A> {}
+
+/// Renders live entries as a JSON object keyed by the keys' `Display` text.
+#[cfg(feature = "json")]
+impl<K: Ord + Display, V: Val<A> + crate::json::ToValue, A: Ord + Hash> crate::json::ToValue
+    for Map<K, V, A>
+{
+    fn to_value(&self) -> crate::json::Value {
+        crate::json::Value::Object(
+            self.entries
+                .iter()
+                .map(|(key, entry)| (key.to_string(), entry.val.to_value()))
+                .collect(),
+        )
+    }
+}
+
+/// Borrows the value under a key without cloning it or its context.
+///
+/// # Panics
+///
+/// Panics if the key is not in the map, use `Map::get` when it may be absent.
+///
+/// ```rust
+/// use crdts::{CmRDT, MVReg, Map};
+///
+/// let mut map: Map<&str, MVReg<u8, &str>, &str> = Map::new();
+/// let add_ctx = map.read_ctx().derive_add_ctx("A");
+/// map.apply(map.update("x", add_ctx, |reg, ctx| reg.write(7, ctx)));
+///
+/// assert_eq!(map[&"x"].read().val, vec![7]);
+/// ```
+impl<K: Ord, V: Val<A>, A: Ord + Hash> Index<&K> for Map<K, V, A> {
+    type Output = V;
+
+    fn index(&self, key: &K) -> &V {
+        &self.entries.get(key).expect("no entry found for key").val
+    }
+}
+
+impl<K: Ord, V: Val<A>, A: Ord + Hash> Concurrency for Map<K, V, A> {}
+
 impl<K: Ord, V: Val<A>, A: Ord + Hash> ResetRemove<A> for Map<K, V, A> {
     fn reset_remove(&mut self, clock: &VClock<A>) {
+        if clock.is_empty() {
+            // nothing to forget, avoid rebuilding the entries and deferred maps
+            return;
+        }
+
         self.entries = mem::take(&mut self.entries)
             .into_iter()
             .filter_map(|(key, mut entry)| {
@@ -102,19 +775,91 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash> ResetRemove<A> for Map<K, V, A> {
             })
             .collect();
 
+        self.clock.reset_remove(clock);
+
+        let map_clock = &self.clock;
         self.deferred = mem::take(&mut self.deferred)
             .into_iter()
             .filter_map(|(mut rm_clock, key)| {
                 rm_clock.reset_remove(clock);
                 if rm_clock.is_empty() {
                     None // this deferred remove has been forgotten
+                } else if rm_clock <= *map_clock {
+                    // what's left of the remove is covered by our clock, it
+                    // has already reset every entry it could reach
+                    None
                 } else {
                     Some((rm_clock, key))
                 }
             })
             .collect();
+    }
+}
 
-        self.clock.reset_remove(clock);
+impl<K: Ord + Clone + Debug, V: Val<A> + CvRDT + Debug, A: Ord + Hash + Clone + Debug> DotStore<A>
+    for Map<K, V, A>
+{
+}
+
+impl<K: Ord, V: Val<A> + LogicalEq, A: Ord + Hash> LogicalEq for Map<K, V, A> {
+    /// Returns true if both maps hold the same keys with logically equal
+    /// values, ignoring the map and entry clocks and the deferred removes
+    /// that `PartialEq` also compares.
+    ///
+    /// Use this to check that replicas agree on what they show users, e.g.
+    /// after a sync, when one of them may still be holding on to history the
+    /// other has already dropped.
+    fn logically_eq(&self, other: &Self) -> bool {
+        self.entries.len() == other.entries.len()
+            && self
+                .entries
+                .iter()
+                .zip(other.entries.iter())
+                .all(|((k, a), (other_k, b))| k == other_k && a.val.logically_eq(&b.val))
+    }
+}
+
+impl<K: Ord, V: Val<A> + HeapSize, A: Ord + Hash> HeapSize for Map<K, V, A> {
+    fn heap_size(&self) -> usize {
+        let entries = self
+            .entries
+            .values()
+            .map(|entry| {
+                mem::size_of::<(K, Entry<V, A>)>() + entry.clock.heap_size() + entry.val.heap_size()
+            })
+            .sum::<usize>();
+        let deferred = self.deferred.capacity() * mem::size_of::<(VClock<A>, BTreeSet<K>)>()
+            + self
+                .deferred
+                .iter()
+                .map(|(clock, keys)| clock.heap_size() + keys.len() * mem::size_of::<K>())
+                .sum::<usize>();
+        let tombstones = self.tombstones.as_ref().map_or(0, |log| {
+            log.removed.capacity() * mem::size_of::<(K, VClock<A>)>()
+                + log
+                    .removed
+                    .iter()
+                    .map(|(_, clock)| clock.heap_size())
+                    .sum::<usize>()
+        });
+        let deferred_limit = self.deferred_limit.as_ref().map_or(0, |bound| {
+            bound.order.capacity() * mem::size_of::<VClock<A>>()
+                + bound
+                    .order
+                    .iter()
+                    .map(|clock| clock.heap_size())
+                    .sum::<usize>()
+        });
+        let capacity = self.capacity.as_ref().map_or(0, |capacity| {
+            (capacity.ticks.len() + capacity.order.len()) * mem::size_of::<(K, u64)>()
+        });
+        self.clock.heap_size() + entries + deferred + tombstones + deferred_limit + capacity
+    }
+}
+
+impl<K: Ord, V: Val<A>, A: Ord + Hash + Clone> CausalContext<A> for Map<K, V, A> {
+    fn causal_context(&self) -> VClock<A> {
+        self.clock.clone()
     }
 }
 
@@ -136,11 +881,101 @@ impl<V: CmRDT + Debug, A: Debug> Display for CmRDTValidation<V, A> {
 
 impl<V: CmRDT + Debug, A: Debug> std::error::Error for CmRDTValidation<V, A> {}
 
-/// The various validation errors that may occur when using a Map CRDT.
+/// Returned by `Map::try_apply` when an op reuses a dot we've already seen
+/// for a different update, usually a sign that two replicas share an actor id.
 #[derive(Debug, PartialEq, Eq)]
-pub enum CvRDTValidation<K, V: CvRDT, A> {
-    /// We've detected that two different members were inserted with the same dot.
-    /// This can break associativity.
+pub struct DotConflict<K, A> {
+    /// The dot that was reused
+    pub dot: Dot<A>,
+    /// The key the conflicting op was updating
+    pub key: K,
+}
+
+impl<K: Debug, A: Debug> Display for DotConflict<K, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "dot {:?} was already used for a different update than the one to {:?}",
+            self.dot, self.key
+        )
+    }
+}
+
+impl<K: Debug, A: Debug> std::error::Error for DotConflict<K, A> {}
+
+/// Returned by `Map::check_invariants` when the map's state is inconsistent.
+#[derive(Debug, PartialEq, Eq)]
+pub enum InvariantError<K, A: Ord> {
+    /// An entry has seen dots that the map clock hasn't.
+    EntryAheadOfClock {
+        /// The key of the entry
+        key: K,
+        /// The clock of the entry
+        entry_clock: VClock<A>,
+        /// The clock of the map
+        clock: VClock<A>,
+    },
+    /// A deferred remove is covered by the map clock, it should have been
+    /// applied rather than deferred.
+    StaleDeferred {
+        /// The clock of the deferred remove
+        rm_clock: VClock<A>,
+        /// The clock of the map
+        clock: VClock<A>,
+    },
+}
+
+impl<K: Debug, A: Ord + Debug> Display for InvariantError<K, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantError::EntryAheadOfClock {
+                key,
+                entry_clock,
+                clock,
+            } => write!(
+                f,
+                "entry {:?} has clock {:?} which is not covered by the map clock {:?}",
+                key, entry_clock, clock
+            ),
+            InvariantError::StaleDeferred { rm_clock, clock } => write!(
+                f,
+                "deferred remove {:?} is covered by the map clock {:?}",
+                rm_clock, clock
+            ),
+        }
+    }
+}
+
+impl<K: Debug, A: Ord + Debug> std::error::Error for InvariantError<K, A> {}
+
+/// Errors reading or writing an op log, see `Map::from_op_log`.
+#[cfg(feature = "json")]
+#[derive(Debug)]
+pub enum OpLogError {
+    /// The log could not be read or written, or ended in the middle of an op.
+    Io(std::io::Error),
+    /// An op in the log could not be encoded or decoded.
+    Decode(serde_json::Error),
+}
+
+#[cfg(feature = "json")]
+impl Display for OpLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpLogError::Io(e) => write!(f, "op log io error: {}", e),
+            OpLogError::Decode(e) => write!(f, "op log encoding error: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl std::error::Error for OpLogError {}
+
+/// The various validation errors that may occur when using a Map CRDT.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CvRDTValidation<K, V: CvRDT, A> {
+    /// We've detected that two different members were inserted with the same dot.
+    /// This can break associativity.
     DoubleSpentDot {
         /// The dot that was double spent
         dot: Dot<A>,
@@ -162,7 +997,7 @@ impl<K: Debug, V: CvRDT + Debug, A: Debug> Display for CvRDTValidation<K, V, A>
 
 impl<K: Debug, V: CvRDT + Debug, A: Debug> std::error::Error for CvRDTValidation<K, V, A> {}
 
-impl<K: Ord, V: Val<A> + Debug, A: Ord + Hash + Clone + Debug> CmRDT for Map<K, V, A> {
+impl<K: Ord + Clone, V: Val<A> + Debug, A: Ord + Hash + Clone + Debug> CmRDT for Map<K, V, A> {
     type Op = Op<K, V, A>;
     type Validation = CmRDTValidation<V, A>;
 
@@ -180,16 +1015,96 @@ impl<K: Ord, V: Val<A> + Debug, A: Ord + Hash + Clone + Debug> CmRDT for Map<K,
                     .map_err(CmRDTValidation::SourceOrder)?;
                 entry.val.validate_op(op).map_err(CmRDTValidation::Value)
             }
+            Op::UpMany { dot, updates } => {
+                self.clock
+                    .validate_op(dot)
+                    .map_err(CmRDTValidation::SourceOrder)?;
+                for ((dot, key), (_, op)) in op.updated_dots().into_iter().zip(updates) {
+                    let entry = self.entries.get(key).cloned().unwrap_or_default();
+                    entry
+                        .clock
+                        .validate_op(&dot)
+                        .map_err(CmRDTValidation::SourceOrder)?;
+                    entry.val.validate_op(op).map_err(CmRDTValidation::Value)?;
+                }
+                Ok(())
+            }
         }
     }
 
     fn apply(&mut self, op: Self::Op) {
+        let updated: Vec<(Dot<A>, K)> = op
+            .updated_dots()
+            .into_iter()
+            .map(|(dot, key)| (dot, key.clone()))
+            .collect();
+        if self.apply_unsettled(op) {
+            self.apply_deferred_to(&updated);
+            self.enforce_capacity();
+        }
+        self.debug_check_invariants();
+    }
+}
+
+impl<K: Ord + Clone, V: Val<A> + Debug, A: Ord + Hash + Clone + Debug> Map<K, V, A> {
+    /// Check the invariants that the map's causal bookkeeping relies on:
+    /// the map clock covers the clock of every entry, and it does not cover
+    /// the clock of any deferred remove, as those are applied as soon as
+    /// it does.
+    ///
+    /// `apply` and `merge` keep these invariants, and check them in debug
+    /// builds. A violation points to a nested CRDT that misbehaves or to
+    /// state corrupted outside of the map's API.
+    pub fn check_invariants(&self) -> Result<(), InvariantError<K, A>> {
+        for (key, entry) in self.entries.iter() {
+            if !matches!(
+                entry.clock.partial_cmp(&self.clock),
+                Some(Ordering::Less | Ordering::Equal)
+            ) {
+                return Err(InvariantError::EntryAheadOfClock {
+                    key: key.clone(),
+                    entry_clock: entry.clock.clone(),
+                    clock: self.clock.clone(),
+                });
+            }
+        }
+        if let Some(rm_clock) = self
+            .deferred
+            .keys()
+            .find(|rm_clock| **rm_clock <= self.clock)
+        {
+            return Err(InvariantError::StaleDeferred {
+                rm_clock: rm_clock.clone(),
+                clock: self.clock.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    fn debug_check_invariants(&self) {
+        debug_assert!(
+            self.check_invariants().is_ok(),
+            "map invariants violated, see Map::check_invariants"
+        );
+    }
+
+    /// Apply an op without applying deferred removes or enforcing the
+    /// capacity policy afterwards, returns true if it was a new update and
+    /// those still need to run.
+    fn apply_unsettled(&mut self, op: Op<K, V, A>) -> bool {
         match op {
-            Op::Rm { clock, keyset } => self.apply_keyset_rm(keyset, clock),
+            Op::Rm { clock, keyset } => {
+                self.apply_keyset_rm(keyset, clock);
+                false
+            }
             Op::Up { dot, key, op } => {
                 if self.clock.get(&dot.actor) >= dot.counter {
                     // we've seen this op already
-                    return;
+                    return false;
+                }
+
+                if let Some(capacity) = self.capacity.as_mut() {
+                    capacity.touch(&key, !self.entries.contains_key(&key));
                 }
 
                 let entry = self.entries.entry(key).or_default();
@@ -198,10 +1113,152 @@ impl<K: Ord, V: Val<A> + Debug, A: Ord + Hash + Clone + Debug> CmRDT for Map<K,
                 entry.val.apply(op);
 
                 self.clock.apply(dot);
-                self.apply_deferred();
+                true
+            }
+            Op::UpMany { dot, updates } => {
+                if updates.is_empty() || self.clock.get(&dot.actor) >= dot.counter {
+                    // we've seen this op already
+                    return false;
+                }
+
+                for (counter, (key, op)) in (dot.counter..).zip(updates) {
+                    if let Some(capacity) = self.capacity.as_mut() {
+                        capacity.touch(&key, !self.entries.contains_key(&key));
+                    }
+
+                    let dot = Dot::new(dot.actor.clone(), counter);
+                    let entry = self.entries.entry(key).or_default();
+                    entry.clock.apply(dot.clone());
+                    entry.val.apply(op);
+                    self.clock.apply(dot);
+                }
+                true
             }
         }
     }
+
+    /// Build the op that writes `val` to the `MVReg` at the end of `path`,
+    /// going through one level of nested maps per key.
+    ///
+    /// Panics if the path does not match the depth of the nesting.
+    ///
+    /// ```rust
+    /// use crdts::{CmRDT, MVReg, Map};
+    ///
+    /// type Inner = Map<&'static str, MVReg<u8, char>, char>;
+    /// let mut map: Map<&str, Inner, char> = Map::new();
+    ///
+    /// let ctx = map.read_ctx().derive_add_ctx('a');
+    /// map.apply(map.nested_put(&["users", "alice"], 42, ctx));
+    ///
+    /// let users = map.get(&"users").val.unwrap();
+    /// assert_eq!(users.get(&"alice").val.unwrap().read().val, vec![42]);
+    /// ```
+    pub fn nested_put<T>(&self, path: &[K], val: T, ctx: AddCtx<A>) -> Op<K, V, A>
+    where
+        Self: NestedPut<K, T, A, Op = Op<K, V, A>>,
+    {
+        self.put_path(path, val, ctx)
+    }
+
+    /// Build the op that applies `leaf_op` to the CRDT at the end of `path`,
+    /// going through one level of nested maps per key.
+    ///
+    /// Unlike nesting `update` calls, which start from a default value
+    /// wherever a key is absent, every map along the path must already hold
+    /// its key. Only the last key may be missing, in which case `leaf_op`
+    /// sees a default leaf. Otherwise `PathError::MissingLevel` gives the
+    /// position of the first missing key.
+    ///
+    /// Panics if the path does not match the depth of the nesting.
+    ///
+    /// ```rust
+    /// use crdts::map::PathError;
+    /// use crdts::{CmRDT, MVReg, Map};
+    ///
+    /// type Inner = Map<&'static str, MVReg<u8, char>, char>;
+    /// let mut map: Map<&str, Inner, char> = Map::new();
+    ///
+    /// let ctx = map.read_ctx().derive_add_ctx('a');
+    /// let err = map.update_path(&["users", "alice"], ctx, |reg: &MVReg<u8, char>, ctx| {
+    ///     reg.write(42, ctx)
+    /// });
+    /// assert_eq!(err, Err(PathError::MissingLevel(0)));
+    /// ```
+    pub fn update_path<L, F>(
+        &self,
+        path: &[K],
+        ctx: AddCtx<A>,
+        leaf_op: F,
+    ) -> Result<Op<K, V, A>, PathError>
+    where
+        Self: NestedUpdate<K, L, A, Op = Op<K, V, A>>,
+        L: CmRDT,
+        F: FnOnce(&L, AddCtx<A>) -> L::Op,
+    {
+        self.update_at(path, 0, ctx, leaf_op)
+    }
+
+    /// Rebuilds a map from an op log written by `Map::write_op_log`.
+    ///
+    /// The ops are applied in order and deferred removes are settled once,
+    /// after the last op, which gives the same map as applying each op with
+    /// `apply`. Errors reading or decoding the log are returned, the ops
+    /// decoded before the error are discarded.
+    #[cfg(feature = "json")]
+    pub fn from_op_log<R: std::io::Read>(mut reader: R) -> Result<Self, OpLogError>
+    where
+        Op<K, V, A>: serde::de::DeserializeOwned,
+    {
+        use std::io::Read;
+
+        let mut map = Self::new();
+        let mut len = [0u8; 8];
+        loop {
+            match reader.read_exact(&mut len) {
+                Ok(()) => (),
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(OpLogError::Io(e)),
+            }
+            // the buffer grows as the record is read rather than trusting a
+            // possibly corrupt length with an allocation up front
+            let len = u64::from_le_bytes(len);
+            let mut record = Vec::new();
+            reader
+                .by_ref()
+                .take(len)
+                .read_to_end(&mut record)
+                .map_err(OpLogError::Io)?;
+            if (record.len() as u64) < len {
+                return Err(OpLogError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+            }
+            let op = serde_json::from_slice(&record).map_err(OpLogError::Decode)?;
+            map.apply_unsettled(op);
+        }
+        map.apply_deferred();
+        Ok(map)
+    }
+
+    /// Writes ops to an op log that `Map::from_op_log` can rebuild a map
+    /// from. Each op is written as its length, a little endian `u64`,
+    /// followed by the op encoded as JSON, so logs can be appended to.
+    #[cfg(feature = "json")]
+    pub fn write_op_log<'a, W: std::io::Write>(
+        mut writer: W,
+        ops: impl IntoIterator<Item = &'a Op<K, V, A>>,
+    ) -> Result<(), OpLogError>
+    where
+        Op<K, V, A>: Serialize + 'a,
+    {
+        for op in ops {
+            let record = serde_json::to_vec(op).map_err(OpLogError::Decode)?;
+            writer
+                .write_all(&(record.len() as u64).to_le_bytes())
+                .and_then(|()| writer.write_all(&record))
+                .map_err(OpLogError::Io)?;
+        }
+        Ok(())
+    }
 }
 
 impl<K: Ord + Clone + Debug, V: Val<A> + CvRDT + Debug, A: Ord + Hash + Clone + Debug> CvRDT
@@ -235,90 +1292,143 @@ impl<K: Ord + Clone + Debug, V: Val<A> + CvRDT + Debug, A: Ord + Hash + Clone +
     }
 
     fn merge(&mut self, other: Self) {
-        self.entries = mem::take(&mut self.entries)
-            .into_iter()
-            .filter_map(|(key, mut entry)| {
-                if !other.entries.contains_key(&key) {
-                    // other doesn't contain this entry because it:
-                    //  1. has seen it and dropped it
-                    //  2. hasn't seen it
-                    if other.clock >= entry.clock {
-                        // other has seen this entry and dropped it
-                        None
-                    } else {
-                        // the other map has not seen this version of this
-                        // entry, so add it. But first, we have to remove any
-                        // information that may have been known at some point
-                        // by the other map about this key and was removed.
-                        entry.clock.reset_remove(&other.clock);
-                        let mut removed_information = other.clock.clone();
-                        removed_information.reset_remove(&entry.clock);
-                        entry.val.reset_remove(&removed_information);
-                        Some((key, entry))
-                    }
-                } else {
-                    Some((key, entry))
-                }
-            })
-            .collect();
+        self.merge_reporting(other, |_, _| (), &mut MergeStats::default());
+        self.debug_check_invariants();
+    }
+}
 
-        for (key, mut entry) in other.entries {
-            if let Some(our_entry) = self.entries.get_mut(&key) {
-                // SUBTLE: this entry is present in both maps, BUT that doesn't mean we
-                // shouldn't drop it!
-                // Perfectly possible that an item in both sets should be dropped
-                let mut common = VClock::intersection(&entry.clock, &our_entry.clock);
-                common.merge(entry.clock.clone_without(&self.clock));
-                common.merge(our_entry.clock.clone_without(&other.clock));
-                if common.is_empty() {
-                    // both maps had seen each others entry and removed them
-                    self.entries.remove(&key).unwrap();
-                } else {
-                    // we should not drop, as there is information still tracked in
-                    // the common clock.
-                    our_entry.val.merge(entry.val);
-
-                    let mut information_that_was_deleted = entry.clock.clone();
-                    information_that_was_deleted.merge(our_entry.clock.clone());
-                    information_that_was_deleted.reset_remove(&common);
-                    our_entry.val.reset_remove(&information_that_was_deleted);
-                    our_entry.clock = common;
-                }
-            } else {
-                // we don't have this entry, is it because we:
-                //  1. have seen it and dropped it
-                //  2. have not seen it
-                if self.clock >= entry.clock {
-                    // We've seen this entry and dropped it, we won't add it back
-                } else {
-                    // We have not seen this version of this entry, so we add it.
-                    // but first, we have to remove the information on this entry
-                    // that we have seen and deleted
-                    entry.clock.reset_remove(&self.clock);
-
-                    let mut information_we_deleted = self.clock.clone();
-                    information_we_deleted.reset_remove(&entry.clock);
-                    entry.val.reset_remove(&information_we_deleted);
-                    self.entries.insert(key, entry);
-                }
-            }
+impl<K: Ord, V: Val<A>, A: Ord + Hash + Clone> Map<K, V, A> {
+    /// Constructs an empty Map
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Constructs a Map seeded with the given data, each value is inserted
+    /// under a fresh dot from `actor` so the resulting map's clock dominates
+    /// every entry.
+    ///
+    /// The values are stored as is, any causal history they carry is the
+    /// caller's responsibility.
+    pub fn from_map(actor: A, data: BTreeMap<K, V>) -> Self {
+        let mut map = Self::new();
+        for (key, val) in data {
+            let dot = map.clock.inc(actor.clone());
+            map.clock.apply_dot(dot.clone());
+            let mut clock = VClock::new();
+            clock.apply_dot(dot);
+            map.entries.insert(key, Entry { clock, val });
         }
+        map
+    }
 
-        // merge deferred removals
-        for (rm_clock, keys) in other.deferred {
-            self.apply_keyset_rm(keys, rm_clock);
+    /// Constructs an empty Map that records every op applied to it that
+    /// changes its state, including ops built with `update` or `rm` and
+    /// applied locally.
+    ///
+    /// ```rust
+    /// use crdts::{CmRDT, MVReg, Map};
+    ///
+    /// let mut map = Map::<&str, MVReg<u8, char>, char>::with_op_recorder();
+    /// let ctx = map.read_ctx().derive_add_ctx('a');
+    /// let op = map.update("x", ctx, |reg, ctx| reg.write(1, ctx));
+    /// map.apply(op.clone());
+    /// map.apply(op);
+    ///
+    /// let log = map.take_log();
+    /// assert_eq!(log.len(), 1);
+    ///
+    /// let mut replayed = Map::new();
+    /// log.into_iter().for_each(|op| replayed.apply(op));
+    /// assert_eq!(replayed, map.into_map());
+    /// ```
+    pub fn with_op_recorder() -> OpRecorder<K, V, A> {
+        OpRecorder {
+            map: Self::new(),
+            log: Vec::new(),
         }
+    }
 
-        self.clock.merge(other.clock);
+    /// Constructs an empty Map that keeps a log of the last `capacity` keys
+    /// dropped by removes along with the clock of the remove that dropped them.
+    ///
+    /// The log is local metadata: it is not merged, serialized or compared.
+    /// Only removes applied on this replica (including deferred removes once
+    /// they take effect) are recorded, keys dropped while merging another
+    /// replica's state are not. A remove that is partially undone by
+    /// concurrent edits leaves the key in the map and is not recorded.
+    pub fn with_tombstone_log(capacity: usize) -> Self {
+        Self {
+            tombstones: Some(TombstoneLog {
+                capacity,
+                removed: VecDeque::new(),
+            }),
+            ..Default::default()
+        }
+    }
 
-        self.apply_deferred();
+    /// Constructs an empty Map that keeps at most `limit` deferred removes
+    /// (removes whose clock has seen updates this map has not). When the
+    /// limit is exceeded the oldest deferred removes are dropped.
+    ///
+    /// This is a safety valve against a peer that is far behind making the
+    /// deferred removes grow without bound, it weakens the guarantees of the
+    /// map: the entries an evicted remove would have removed survive once
+    /// they arrive, and this map may diverge from replicas that kept it.
+    /// Use `Map::evicted_deferred` to monitor how often this happens.
+    pub fn with_deferred_limit(limit: usize) -> Self {
+        Self {
+            deferred_limit: Some(DeferredLimit {
+                limit,
+                order: VecDeque::new(),
+                evicted: 0,
+            }),
+            ..Default::default()
+        }
     }
-}
 
-impl<K: Ord, V: Val<A>, A: Ord + Hash + Clone> Map<K, V, A> {
-    /// Constructs an empty Map
-    pub fn new() -> Self {
-        Default::default()
+    /// Constructs an empty Map that holds at most `capacity` entries, once
+    /// an update or merge goes over capacity entries are evicted according
+    /// to `policy`.
+    ///
+    /// Eviction drops entries locally, it is not a CRDT operation: evicted
+    /// entries come back (with only the edits made after their eviction) if
+    /// the map keeps receiving ops or merging with replicas that still have
+    /// them. Only use it for caches and other maps that are not replicated.
+    pub fn with_capacity_policy(capacity: usize, policy: EvictionPolicy) -> Self {
+        Self {
+            capacity: Some(CapacityPolicy {
+                capacity,
+                policy,
+                tick: 0,
+                ticks: BTreeMap::new(),
+                order: BTreeMap::new(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the number of deferred removes dropped so far because of the
+    /// limit set with `Map::with_deferred_limit`.
+    pub fn evicted_deferred(&self) -> usize {
+        self.deferred_limit
+            .as_ref()
+            .map(|bound| bound.evicted)
+            .unwrap_or(0)
+    }
+
+    /// Returns the logged removes, oldest first. Always empty unless the map
+    /// was built with `Map::with_tombstone_log`.
+    pub fn tombstones(&self) -> impl Iterator<Item = &(K, VClock<A>)> {
+        self.tombstones.iter().flat_map(|log| log.removed.iter())
+    }
+
+    /// Returns true if the map is causally indistinguishable from
+    /// `Map::default()`: no entries, no deferred removes and an empty clock.
+    ///
+    /// Unlike `is_empty`, a map whose entries have all been removed is not
+    /// pristine since its clock still carries the history of those entries.
+    pub fn is_default(&self) -> bool {
+        self.clock.is_empty() && self.entries.is_empty() && self.deferred.is_empty()
     }
 
     /// Returns true if the map has no entries, false otherwise
@@ -339,6 +1449,11 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash + Clone> Map<K, V, A> {
         }
     }
 
+    /// Borrow the value stored under a key.
+    pub(crate) fn val(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|entry| &entry.val)
+    }
+
     /// Retrieve value stored under a key
     pub fn get(&self, key: &K) -> ReadCtx<Option<V>, A> {
         let add_clock = self.clock.clone();
@@ -352,6 +1467,76 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash + Clone> Map<K, V, A> {
         }
     }
 
+    /// Retrieve a reference to the value stored under a key
+    ///
+    /// The context is the same as the one returned by `get`, but the value
+    /// is borrowed instead of cloned, which matters for large nested values.
+    pub fn get_ref(&self, key: &K) -> ReadCtx<Option<&V>, A> {
+        let entry_opt = self.entries.get(key);
+        ReadCtx {
+            add_clock: self.clock.clone(),
+            rm_clock: entry_opt
+                .map(|map_entry| map_entry.clock.clone())
+                .unwrap_or_default(),
+            val: entry_opt.map(|map_entry| &map_entry.val),
+        }
+    }
+
+    /// Retrieve the clock of the entry stored under a key.
+    ///
+    /// Unlike the `rm_clock` returned by `get`, this distinguishes an absent
+    /// key (`None`) from a present one, making it suitable for building an
+    /// `RmCtx` that removes exactly the observed version of an entry.
+    pub fn entry_clock(&self, key: &K) -> Option<VClock<A>> {
+        self.entries.get(key).map(|entry| entry.clock.clone())
+    }
+
+    /// Returns the entries that were edited by updates not seen by `since`,
+    /// in key order.
+    ///
+    /// Only live entries are returned: keys removed since `since` don't show
+    /// up, compare `keys` between polls to find those.
+    pub fn entries_since(&self, since: &VClock<A>) -> Vec<(K, V)>
+    where
+        K: Clone,
+    {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| {
+                matches!(
+                    entry.clock.partial_cmp(since),
+                    None | Some(Ordering::Greater)
+                )
+            })
+            .map(|(key, entry)| (key.clone(), entry.val.clone()))
+            .collect()
+    }
+
+    /// Returns the keys that this map and `other` created independently of
+    /// each other, in key order.
+    ///
+    /// A key counts as independently created when both maps hold it, neither
+    /// entry clock has seen the other, and the two clocks share no history at
+    /// all. Concurrent edits of a key both sides already knew about share the
+    /// dots of its creation and are not reported. Call this before merging,
+    /// once merged the two histories can no longer be told apart.
+    pub fn independently_created_keys(&self, other: &Self) -> Vec<K>
+    where
+        K: Clone,
+    {
+        self.entries
+            .iter()
+            .filter(|(key, entry)| {
+                other.entries.get(key).is_some_and(|theirs| {
+                    let mut shared = entry.clock.clone();
+                    shared.glb(&theirs.clock);
+                    entry.clock.concurrent(&theirs.clock) && shared.is_empty()
+                })
+            })
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
     /// Update a value under some key.
     ///
     /// If the key is not present in the map, the updater will be given the
@@ -375,6 +1560,179 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash + Clone> Map<K, V, A> {
         Op::Up { dot, key, op }
     }
 
+    /// Update a value under some key, using `init` instead of `V::default()`
+    /// as the value `f` sees when the key is absent.
+    ///
+    /// Only op generation is affected: replicas applying the op still start
+    /// the entry from `V::default()`, so `f` should produce an op that is
+    /// meaningful on top of either. This is useful when the default value is
+    /// a poor starting point for building the op, e.g. when `f` reads
+    /// configuration from the current value.
+    pub fn update_init<F, G>(&self, key: impl Into<K>, ctx: AddCtx<A>, init: G, f: F) -> Op<K, V, A>
+    where
+        F: FnOnce(&V, AddCtx<A>) -> V::Op,
+        G: FnOnce() -> V,
+    {
+        let key = key.into();
+        let dot = ctx.dot.clone();
+        let op = match self.entries.get(&key).map(|e| &e.val) {
+            Some(data) => f(data, ctx),
+            None => f(&init(), ctx),
+        };
+
+        Op::Up { dot, key, op }
+    }
+
+    /// Update a value under some key as `update` does, but only if the
+    /// entry's clock is still `expected_clock`, returning `None` otherwise.
+    ///
+    /// Take `expected_clock` from the `rm_clock` of an earlier read of the
+    /// entry, e.g. `Map::get`; an absent entry has an empty clock. This is a
+    /// compare-and-swap against what this replica has seen: a write made
+    /// concurrently on another replica is not detected and both writes are
+    /// kept once the replicas sync, as with any concurrent updates.
+    ///
+    /// ```rust
+    /// use crdts::{CmRDT, MVReg, Map};
+    ///
+    /// let mut map: Map<&str, MVReg<u8, char>, char> = Map::new();
+    /// let read = map.get(&"x");
+    /// let expected = read.rm_clock.clone();
+    ///
+    /// let add_ctx = map.read_ctx().derive_add_ctx('a');
+    /// map.apply(map.update("x", add_ctx, |reg, ctx| reg.write(1, ctx)));
+    ///
+    /// // the entry changed since our read, so the write is refused
+    /// let add_ctx = map.read_ctx().derive_add_ctx('a');
+    /// assert!(map
+    ///     .update_if_unchanged("x", expected, add_ctx, |reg, ctx| reg.write(2, ctx))
+    ///     .is_none());
+    /// ```
+    pub fn update_if_unchanged<F>(
+        &self,
+        key: impl Into<K>,
+        expected_clock: VClock<A>,
+        ctx: AddCtx<A>,
+        f: F,
+    ) -> Option<Op<K, V, A>>
+    where
+        F: FnOnce(&V, AddCtx<A>) -> V::Op,
+    {
+        let key = key.into();
+        let unchanged = match self.entries.get(&key) {
+            Some(entry) => entry.clock == expected_clock,
+            None => expected_clock.is_empty(),
+        };
+        if unchanged {
+            Some(self.update(key, ctx, f))
+        } else {
+            None
+        }
+    }
+
+    /// Update or remove the entry under some key depending on the kind of
+    /// context given: an add context updates the value with `f` as `update`
+    /// does, a remove context removes the entry as `rm` does and `f` is not
+    /// called.
+    ///
+    /// ```rust
+    /// use crdts::ctx::WriteCtx;
+    /// use crdts::{CmRDT, MVReg, Map};
+    ///
+    /// let mut map: Map<&str, MVReg<u8, &str>, &str> = Map::new();
+    /// let writes: Vec<(&str, Option<u8>)> = vec![("x", Some(1)), ("y", Some(2)), ("x", None)];
+    /// for (key, val) in writes {
+    ///     let ctx: WriteCtx<_> = match val {
+    ///         Some(_) => map.read_ctx().derive_add_ctx("A").into(),
+    ///         None => map.get(&key).derive_rm_ctx().into(),
+    ///     };
+    ///     map.apply(map.write(key, ctx, |reg, ctx| reg.write(val.unwrap(), ctx)));
+    /// }
+    ///
+    /// assert_eq!(map.get(&"x").val, None);
+    /// assert_eq!(map.get(&"y").val.unwrap().read().val, vec![2]);
+    /// ```
+    pub fn write<F>(&self, key: impl Into<K>, ctx: WriteCtx<A>, f: F) -> Op<K, V, A>
+    where
+        F: FnOnce(&V, AddCtx<A>) -> V::Op,
+    {
+        match ctx {
+            WriteCtx::Add(ctx) => self.update(key, ctx, f),
+            WriteCtx::Rm(ctx) => self.rm(key, ctx),
+        }
+    }
+
+    /// Update the values under several keys in a single op.
+    ///
+    /// `f` is called once per key with the current value (or the default if
+    /// the key is missing) and a context holding that key's dot. The keys get
+    /// consecutive dots starting from `ctx.dot`, and the resulting op applies
+    /// either all of the updates or, if it has been seen before, none.
+    /// Duplicate keys are skipped.
+    ///
+    /// ```rust
+    /// use crdts::{CmRDT, MVReg, Map};
+    ///
+    /// let mut map: Map<&str, MVReg<u8, &str>, &str> = Map::new();
+    /// let ctx = map.read_ctx().derive_add_ctx("A");
+    /// map.apply(map.update_many(vec!["x", "y"], ctx, |key, reg, ctx| {
+    ///     reg.write(if *key == "x" { 1 } else { 2 }, ctx)
+    /// }));
+    ///
+    /// assert_eq!(map.get(&"x").val.unwrap().read().val, vec![1]);
+    /// assert_eq!(map.get(&"y").val.unwrap().read().val, vec![2]);
+    /// ```
+    pub fn update_many<I, F>(&self, keys: I, ctx: AddCtx<A>, mut f: F) -> Op<K, V, A>
+    where
+        I: IntoIterator,
+        I::Item: Into<K>,
+        F: FnMut(&K, &V, AddCtx<A>) -> V::Op,
+    {
+        let AddCtx { mut clock, dot } = ctx;
+        let mut updates: Vec<(K, V::Op)> = Vec::new();
+        for key in keys {
+            let key = key.into();
+            if updates.iter().any(|(k, _)| k == &key) {
+                continue;
+            }
+            let key_dot = Dot::new(dot.actor.clone(), dot.counter + updates.len() as u64);
+            clock.apply_dot(key_dot.clone());
+            let key_ctx = AddCtx {
+                clock: clock.clone(),
+                dot: key_dot,
+            };
+            let op = match self.entries.get(&key).map(|e| &e.val) {
+                Some(data) => f(&key, data, key_ctx),
+                None => f(&key, &V::default(), key_ctx),
+            };
+            updates.push((key, op));
+        }
+        Op::UpMany { dot, updates }
+    }
+
+    /// Update a value under some key with an updater that may fail.
+    ///
+    /// This behaves exactly like `update`, except that if the updater returns
+    /// an error, no Op is generated and the error is returned to the caller.
+    pub fn try_update<F, E>(
+        &self,
+        key: impl Into<K>,
+        ctx: AddCtx<A>,
+        f: F,
+    ) -> Result<Op<K, V, A>, E>
+    where
+        F: FnOnce(&V, AddCtx<A>) -> Result<V::Op, E>,
+    {
+        let key = key.into();
+        let dot = ctx.dot.clone();
+        let op = match self.entries.get(&key).map(|e| &e.val) {
+            Some(data) => f(data, ctx)?,
+            None => f(&V::default(), ctx)?,
+        };
+
+        Ok(Op::Up { dot, key, op })
+    }
+
     /// Remove an entry from the Map
     ///
     /// The `impl Into<K>` bound provides a nice way of providing an input key that
@@ -389,6 +1747,272 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash + Clone> Map<K, V, A> {
         }
     }
 
+    /// Shrinks an op log without changing the state it produces when
+    /// applied, in order, to any map.
+    ///
+    /// The following ops are dropped or merged:
+    /// - updates whose dot is covered by an earlier update from the same
+    ///   actor, `apply` would ignore them anyway.
+    /// - removes with an empty clock, they can't remove anything.
+    /// - consecutive removes under the same clock are merged into one.
+    ///
+    /// Updates that are later removed are kept: their dots are part of the
+    /// map clock, which later removes and deferred removes are compared
+    /// against, so dropping them would change the resulting state.
+    pub fn compact_ops(ops: &[Op<K, V, A>]) -> Vec<Op<K, V, A>>
+    where
+        K: Clone,
+        V::Op: Clone,
+    {
+        let mut seen = VClock::new();
+        let mut compacted: Vec<Op<K, V, A>> = Vec::with_capacity(ops.len());
+        for op in ops {
+            match op {
+                Op::Up { dot, .. } => {
+                    if seen.apply_dot(dot.clone()) {
+                        compacted.push(op.clone());
+                    }
+                }
+                Op::UpMany { dot, updates } => {
+                    if !updates.is_empty() && seen.get(&dot.actor) < dot.counter {
+                        let last = dot.counter + updates.len() as u64 - 1;
+                        seen.apply_dot(Dot::new(dot.actor.clone(), last));
+                        compacted.push(op.clone());
+                    }
+                }
+                Op::Rm { clock, .. } if clock.is_empty() => (),
+                Op::Rm { clock, keyset } => match compacted.last_mut() {
+                    Some(Op::Rm {
+                        clock: prev_clock,
+                        keyset: prev_keyset,
+                    }) if prev_clock == clock => prev_keyset.extend(keyset.iter().cloned()),
+                    _ => compacted.push(op.clone()),
+                },
+            }
+        }
+        compacted
+    }
+
+    /// Locally drop every entry for which `pred` returns false.
+    ///
+    /// This is not a CRDT operation: no op is produced and the dropped
+    /// entries will reappear (or cause divergence) if this map keeps merging
+    /// with replicas that still hold them. Use it only on maps that are no
+    /// longer replicated, e.g. local indexes derived from a CRDT, and use
+    /// `Map::rm` otherwise.
+    ///
+    /// The map clock is left untouched: it is what stops ops this map has
+    /// already seen from being applied again, so shrinking it is never safe.
+    pub fn retain<P: FnMut(&K, &V) -> bool>(&mut self, mut pred: P) {
+        self.entries.retain(|key, entry| pred(key, &entry.val));
+    }
+
+    /// Returns the keys whose values hold more than one concurrent version,
+    /// along with how many versions each holds, e.g. an `MVReg` that saw
+    /// concurrent writes.
+    pub fn conflicts(&self) -> Vec<(K, usize)>
+    where
+        K: Clone,
+        V: Concurrency,
+    {
+        self.entries
+            .iter()
+            .map(|(key, entry)| (key, entry.val.concurrency()))
+            .filter(|(_, concurrency)| *concurrency > 1)
+            .map(|(key, concurrency)| (key.clone(), concurrency))
+            .collect()
+    }
+
+    /// Apply an op and call `observer` with it if the op changed this map.
+    ///
+    /// Ops this map has already seen, and removes that touch nothing we hold
+    /// or have deferred, are dropped without calling the observer. Returns
+    /// whether the observer fired.
+    pub fn apply_observed<F>(&mut self, op: Op<K, V, A>, observer: F) -> bool
+    where
+        F: FnOnce(&Op<K, V, A>),
+        K: Clone,
+        V: Debug,
+        A: Debug,
+        Op<K, V, A>: Clone,
+    {
+        if !self.apply_checked(&op) {
+            return false;
+        }
+        observer(&op);
+        true
+    }
+
+    /// Apply an op, returning true if it changed this map.
+    ///
+    /// Ops this map has already seen, and removes that touch nothing we hold
+    /// or have deferred, are dropped and return false, e.g. so redundant ops
+    /// don't trigger downstream notifications.
+    pub fn apply_checked(&mut self, op: &Op<K, V, A>) -> bool
+    where
+        K: Clone,
+        V: Debug,
+        A: Debug,
+        Op<K, V, A>: Clone,
+    {
+        if !self.changed_by(op) {
+            return false;
+        }
+        self.apply(op.clone());
+        true
+    }
+
+    /// Apply an op, reporting updates that reuse a dot we've already seen.
+    ///
+    /// `apply` silently drops any update whose dot is covered by our clock.
+    /// That's what makes replays idempotent, but it also hides a second,
+    /// different update minted with the same dot by a misconfigured actor.
+    /// This flags an update whose dot we've seen while the key it targets
+    /// doesn't hold that dot, i.e. the dot was spent elsewhere, whether the
+    /// key is present or not.
+    ///
+    /// We don't keep the ops themselves, so two different updates to the
+    /// same key with the same dot go undetected. Nor can we tell a spent dot
+    /// from one that was removed, so a replay of an update whose effects
+    /// have since been removed is also reported. Either way the op is a
+    /// no-op, so on error the map is left untouched.
+    pub fn try_apply(&mut self, op: Op<K, V, A>) -> Result<(), DotConflict<K, A>>
+    where
+        K: Clone,
+        V: Debug,
+        A: Debug,
+    {
+        for (dot, key) in op.updated_dots() {
+            let held = self
+                .entries
+                .get(key)
+                .map_or(0, |entry| entry.clock.get(&dot.actor));
+            if self.clock.get(&dot.actor) >= dot.counter && held < dot.counter {
+                return Err(DotConflict {
+                    dot,
+                    key: key.clone(),
+                });
+            }
+        }
+        self.apply(op);
+        Ok(())
+    }
+
+    /// Returns true if applying this op would change our state.
+    fn changed_by(&self, op: &Op<K, V, A>) -> bool {
+        match op {
+            Op::Up { dot, .. } => self.clock.get(&dot.actor) < dot.counter,
+            Op::UpMany { dot, updates } => {
+                !updates.is_empty() && self.clock.get(&dot.actor) < dot.counter
+            }
+            Op::Rm { clock, keyset } => {
+                let removes_dots = keyset.iter().any(|key| {
+                    self.entries.get(key).is_some_and(|entry| {
+                        entry
+                            .clock
+                            .iter()
+                            .any(|dot| dot.counter <= clock.get(dot.actor))
+                    })
+                });
+                let deferred = matches!(self.clock.partial_cmp(clock), None | Some(Ordering::Less))
+                    && !self
+                        .deferred
+                        .get(clock)
+                        .is_some_and(|deferred| deferred.is_superset(keyset));
+                removes_dots || deferred
+            }
+        }
+    }
+
+    /// Release memory held by the map's hash based buffers beyond what its
+    /// current state needs, e.g. after a burst of deferred removes.
+    ///
+    /// Entries live in a `BTreeMap`, which frees nodes as entries are removed
+    /// and has no spare capacity to reclaim. Nested values are left as is.
+    /// This has no effect on the map's state.
+    pub fn shrink_to_fit(&mut self) {
+        self.deferred.shrink_to_fit();
+        if let Some(log) = self.tombstones.as_mut() {
+            log.removed.shrink_to_fit();
+        }
+        if let Some(bound) = self.deferred_limit.as_mut() {
+            bound.order.shrink_to_fit();
+        }
+    }
+
+    /// Forget everything covered by `clock`, same as `ResetRemove::reset_remove`,
+    /// and return the forgotten portion as a map of its own.
+    ///
+    /// Entries that are forgotten outright come back whole. Entries that
+    /// survive with a smaller clock come back with the dots that were dropped,
+    /// holding the parts of their value that did not survive. Useful to log or
+    /// forward what a garbage collection pass threw away.
+    ///
+    /// ```rust
+    /// use crdts::{CmRDT, Map, MVReg, VClock};
+    ///
+    /// let mut map: Map<&str, MVReg<u8, &str>, &str> = Map::new();
+    /// let ctx = map.read_ctx().derive_add_ctx("a");
+    /// map.apply(map.update("x", ctx, |reg, ctx| reg.write(1, ctx)));
+    /// let ctx = map.read_ctx().derive_add_ctx("b");
+    /// map.apply(map.update("y", ctx, |reg, ctx| reg.write(2, ctx)));
+    ///
+    /// let removed = map.truncate_returning(&VClock::from_pairs([("a", 1)]));
+    /// assert_eq!(removed.keys().map(|k| *k.val).collect::<Vec<_>>(), vec!["x"]);
+    /// assert_eq!(map.keys().map(|k| *k.val).collect::<Vec<_>>(), vec!["y"]);
+    /// ```
+    pub fn truncate_returning(&mut self, clock: &VClock<A>) -> Self
+    where
+        K: Clone,
+    {
+        let mut removed = Self::new();
+        if clock.is_empty() {
+            return removed;
+        }
+
+        for (key, entry) in self.entries.iter_mut() {
+            let mut kept_clock = entry.clock.clone();
+            kept_clock.reset_remove(clock);
+            if kept_clock == entry.clock {
+                continue;
+            }
+            // a dot is dropped iff its counter is covered by `clock`, so the
+            // dropped dots are exactly the ones not left in `kept_clock`
+            let mut dropped_clock = mem::replace(&mut entry.clock, kept_clock);
+            dropped_clock.reset_remove(&entry.clock);
+            let mut dropped_val = entry.val.clone();
+            dropped_val.reset_remove(&entry.clock);
+            entry.val.reset_remove(clock);
+            removed.entries.insert(
+                key.clone(),
+                Entry {
+                    clock: dropped_clock,
+                    val: dropped_val,
+                },
+            );
+        }
+        self.entries.retain(|_, entry| !entry.clock.is_empty());
+
+        for (rm_clock, keys) in mem::take(&mut self.deferred) {
+            let mut kept_clock = rm_clock.clone();
+            kept_clock.reset_remove(clock);
+            if kept_clock != rm_clock {
+                let mut dropped_clock = rm_clock;
+                dropped_clock.reset_remove(&kept_clock);
+                removed.deferred.insert(dropped_clock, keys.clone());
+            }
+            if !kept_clock.is_empty() {
+                self.deferred.insert(kept_clock, keys);
+            }
+        }
+
+        let mut kept_clock = self.clock.clone();
+        kept_clock.reset_remove(clock);
+        removed.clock = mem::replace(&mut self.clock, kept_clock);
+        removed.clock.reset_remove(&self.clock);
+        removed
+    }
+
     /// Retrieve the current read context
     pub fn read_ctx(&self) -> ReadCtx<(), A> {
         ReadCtx {
@@ -399,29 +2023,55 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash + Clone> Map<K, V, A> {
     }
 
     /// apply the pending deferred removes
+    ///
+    /// Every remove is re-applied to all of its keys, as entries may have
+    /// come in from anywhere, see `apply_deferred_to` for applying an op.
     fn apply_deferred(&mut self) {
-        let deferred = mem::take(&mut self.deferred);
-        for (clock, keys) in deferred {
-            self.apply_keyset_rm(keys, clock);
-        }
+        let Map {
+            clock,
+            entries,
+            deferred,
+            tombstones,
+            ..
+        } = self;
+
+        // Deferred removes are re-applied in place, only the removes that
+        // are now fully covered by our clock are dropped, the rest are left
+        // untouched for the next round.
+        deferred.retain(|rm_clock, keyset| {
+            Self::reset_remove_keys(entries, tombstones, keyset, rm_clock);
+            matches!((*clock).partial_cmp(rm_clock), None | Some(Ordering::Less))
+        });
     }
 
-    /// Apply a set of key removals given a clock.
-    fn apply_keyset_rm(&mut self, mut keyset: BTreeSet<K>, clock: VClock<A>) {
-        for key in keyset.iter() {
-            if let Some(entry) = self.entries.get_mut(key) {
-                entry.clock.reset_remove(&clock);
-                if entry.clock.is_empty() {
-                    // The entry clock says we have no info on this entry.
-                    // So remove the entry
-                    self.entries.remove(key);
-                } else {
-                    // The entry clock is not empty so this means we still
-                    // have some information on this entry, keep it.
-                    entry.val.reset_remove(&clock);
+    /// apply the pending deferred removes after an op wrote the given dots
+    ///
+    /// Only the written entries changed, and a remove can only take a dot
+    /// out of them if its clock covers that dot, so only those removes are
+    /// re-applied and only to those keys. The removes now covered by our
+    /// clock are dropped as in `apply_deferred`.
+    fn apply_deferred_to(&mut self, updated: &[(Dot<A>, K)]) {
+        let Map {
+            clock,
+            entries,
+            deferred,
+            tombstones,
+            ..
+        } = self;
+
+        deferred.retain(|rm_clock, keyset| {
+            for (dot, key) in updated {
+                if rm_clock.get(&dot.actor) >= dot.counter && keyset.contains(key) {
+                    Self::reset_remove_key(entries, tombstones, key, rm_clock);
                 }
             }
-        }
+            matches!((*clock).partial_cmp(rm_clock), None | Some(Ordering::Less))
+        });
+    }
+
+    /// Apply a set of key removals given a clock.
+    fn apply_keyset_rm(&mut self, mut keyset: BTreeSet<K>, clock: VClock<A>) {
+        Self::reset_remove_keys(&mut self.entries, &mut self.tombstones, &keyset, &clock);
 
         // now we need to decide wether we should be keeping this
         // remove Op around to remove entries we haven't seen yet.
@@ -431,13 +2081,88 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash + Clone> Map<K, V, A> {
                 // we need to log this in our deferred remove map, so
                 // that we can delete keys that we haven't seen yet but
                 // have been seen by this clock
+                if let Some(bound) = self.deferred_limit.as_mut() {
+                    if !bound.order.contains(&clock) {
+                        bound.order.push_back(clock.clone());
+                    }
+                }
                 let deferred_set = self.deferred.entry(clock).or_default();
                 deferred_set.append(&mut keyset);
+                self.enforce_deferred_limit();
             }
             _ => { /* we've seen all keys this clock has seen */ }
         }
     }
 
+    /// Evict entries until we are within the capacity policy, if any.
+    fn enforce_capacity(&mut self)
+    where
+        K: Clone,
+    {
+        if let Some(capacity) = self.capacity.as_mut() {
+            capacity.evict(&mut self.entries);
+        }
+    }
+
+    /// Drop the oldest deferred removes until we are within the deferred limit.
+    fn enforce_deferred_limit(&mut self) {
+        let Map {
+            deferred,
+            deferred_limit,
+            ..
+        } = self;
+        if let Some(bound) = deferred_limit {
+            // forget the clocks of deferred removes that have since been applied
+            bound.order.retain(|clock| deferred.contains_key(clock));
+            while deferred.len() > bound.limit {
+                match bound.order.pop_front() {
+                    Some(clock) => {
+                        deferred.remove(&clock);
+                        bound.evicted += 1;
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    /// Forget the information seen by `clock` from the entries under the given keys.
+    fn reset_remove_keys(
+        entries: &mut BTreeMap<K, Entry<V, A>>,
+        tombstones: &mut Option<TombstoneLog<K, A>>,
+        keyset: &BTreeSet<K>,
+        clock: &VClock<A>,
+    ) {
+        for key in keyset.iter() {
+            Self::reset_remove_key(entries, tombstones, key, clock);
+        }
+    }
+
+    /// Forget the information seen by `clock` from the entry under `key`.
+    fn reset_remove_key(
+        entries: &mut BTreeMap<K, Entry<V, A>>,
+        tombstones: &mut Option<TombstoneLog<K, A>>,
+        key: &K,
+        clock: &VClock<A>,
+    ) {
+        if let Some(entry) = entries.get_mut(key) {
+            entry.clock.reset_remove(clock);
+            if entry.clock.is_empty() {
+                // The entry clock says we have no info on this entry.
+                // So remove the entry
+                if let (Some((key, _)), Some(log)) =
+                    (entries.remove_entry(key), tombstones.as_mut())
+                {
+                    log.record(key, clock.clone());
+                }
+            } else {
+                // The entry clock is not empty so this means we still
+                // have some information on this entry, keep it.
+                entry.val.reset_remove(clock);
+            }
+        }
+    }
+
     /// Gets an iterator over the keys of the `Map`.
     ///
     /// # Examples
@@ -563,6 +2288,606 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash + Clone> Map<K, V, A> {
             val: (k, &v.val),
         })
     }
+
+    /// Gets an iterator over the entries of the `Map` whose keys fall in
+    /// `range`, in key order.
+    ///
+    /// Only the entries in the range are visited, the rest of the map isn't
+    /// scanned. Removed keys are never yielded.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use crdts::{CmRDT, MVReg, Map};
+    ///
+    /// let mut map: Map<u8, MVReg<&str, u8>, u8> = Map::new();
+    /// for (key, val) in [(1, "a"), (5, "b"), (7, "c"), (9, "d")] {
+    ///     let add_ctx = map.read_ctx().derive_add_ctx(1);
+    ///     map.apply(map.update(key, add_ctx, |v, a| v.write(val, a)));
+    /// }
+    ///
+    /// let keys: Vec<_> = map.range(5..9).map(|item_ctx| *item_ctx.val.0).collect();
+    /// assert_eq!(keys, &[5, 7]);
+    /// ```
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> impl Iterator<Item = ReadCtx<(&K, &V), A>> {
+        self.entries.range(range).map(move |(k, v)| ReadCtx {
+            add_clock: self.clock.clone(),
+            rm_clock: v.clock.clone(),
+            val: (k, &v.val),
+        })
+    }
+
+    /// Gets an iterator over the entries of the `Map` that `actor` has
+    /// written to, in key order.
+    ///
+    /// An entry qualifies while its clock holds a dot of `actor`, so writes
+    /// that a later remove has reset no longer count.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use crdts::{CmRDT, MVReg, Map};
+    ///
+    /// let mut map: Map<u8, MVReg<&str, char>, char> = Map::new();
+    /// for (key, actor) in [(1, 'a'), (2, 'b'), (3, 'a')] {
+    ///     let add_ctx = map.read_ctx().derive_add_ctx(actor);
+    ///     map.apply(map.update(key, add_ctx, |v, a| v.write("x", a)));
+    /// }
+    ///
+    /// let keys: Vec<_> = map.entries_by_actor(&'a').map(|(k, _)| *k).collect();
+    /// assert_eq!(keys, &[1, 3]);
+    /// ```
+    pub fn entries_by_actor<'a>(&'a self, actor: &'a A) -> impl Iterator<Item = (&'a K, &'a V)> {
+        self.entries
+            .iter()
+            .filter(move |(_, entry)| entry.clock.get(actor) > 0)
+            .map(|(k, entry)| (k, &entry.val))
+    }
+
+    /// Move the entries with keys in `range` out into a map of their own,
+    /// e.g. to hand a key range over to another shard.
+    ///
+    /// Both maps keep this map's clock, and each takes the deferred removes
+    /// of its own keys. Rejoin the shards with `Map::rejoin`: merging them
+    /// with `CvRDT::merge` would read the keys missing from each shard as
+    /// removed.
+    ///
+    /// ```rust
+    /// use crdts::{CmRDT, MVReg, Map};
+    ///
+    /// let mut map: Map<u8, MVReg<&str, u8>, u8> = Map::new();
+    /// for (key, val) in [(1, "a"), (5, "b"), (7, "c"), (9, "d")] {
+    ///     let add_ctx = map.read_ctx().derive_add_ctx(1);
+    ///     map.apply(map.update(key, add_ctx, |v, a| v.write(val, a)));
+    /// }
+    /// let original = map.clone();
+    ///
+    /// let shard = map.extract(5..9);
+    /// assert_eq!(shard.keys().map(|k| *k.val).collect::<Vec<_>>(), &[5, 7]);
+    /// assert_eq!(map.keys().map(|k| *k.val).collect::<Vec<_>>(), &[1, 9]);
+    ///
+    /// map.rejoin(shard);
+    /// assert_eq!(map, original);
+    /// ```
+    pub fn extract<R: RangeBounds<K>>(&mut self, range: R) -> Self {
+        let (entries, rest) = mem::take(&mut self.entries)
+            .into_iter()
+            .partition(|(key, _)| range.contains(key));
+        self.entries = rest;
+
+        let mut deferred = HashMap::new();
+        self.deferred = mem::take(&mut self.deferred)
+            .into_iter()
+            .filter_map(|(clock, keys)| {
+                let (moved, kept): (BTreeSet<K>, BTreeSet<K>) =
+                    keys.into_iter().partition(|key| range.contains(key));
+                if moved.is_empty() {
+                    return Some((clock, kept));
+                }
+                if kept.is_empty() {
+                    deferred.insert(clock, moved);
+                    return None;
+                }
+                deferred.insert(clock.clone(), moved);
+                Some((clock, kept))
+            })
+            .collect();
+
+        Map {
+            clock: self.clock.clone(),
+            entries,
+            deferred,
+            tombstones: None,
+            deferred_limit: None,
+            capacity: None,
+        }
+    }
+
+    /// Take back the entries of a shard split off with `Map::extract`.
+    ///
+    /// The shard's entries and deferred removes are added as they are and
+    /// the clocks are joined, undoing the split even if both maps saw more
+    /// ops since. Keys held by both maps are taken from the shard. Deferred
+    /// removes from either map are then applied to the joined entries, as a
+    /// remove one map saw may cover a write that only reached the other.
+    pub fn rejoin(&mut self, shard: Self) {
+        self.entries.extend(shard.entries);
+        for (clock, keys) in shard.deferred {
+            self.deferred.entry(clock).or_default().extend(keys);
+        }
+        for dot in shard.clock {
+            self.clock.apply_dot(dot);
+        }
+        self.apply_deferred();
+    }
+}
+
+impl<K: Ord + Clone + Debug, V: Val<A> + CvRDT + Debug, A: Ord + Hash + Clone + Debug>
+    Map<K, V, A>
+{
+    /// Merge the given map into this one, calling `on_progress` with the
+    /// number of entries processed so far and the total number of entries
+    /// across both maps.
+    ///
+    /// The callback fires every few thousand entries and once more with
+    /// `(total, total)` when the merge is done, so large merges can report
+    /// progress. The result is the same as `merge`.
+    pub fn merge_with_progress(&mut self, other: Self, on_progress: impl FnMut(usize, usize)) {
+        self.merge_reporting(other, on_progress, &mut MergeStats::default());
+        self.debug_check_invariants();
+    }
+
+    /// Merge the given map into this one, returning how often each of the
+    /// merge's decisions was taken, see `MergeStats`.
+    ///
+    /// The result is the same as `merge`.
+    pub fn merge_with_stats(&mut self, other: Self) -> MergeStats {
+        let mut stats = MergeStats::default();
+        self.merge_reporting(other, |_, _| (), &mut stats);
+        self.debug_check_invariants();
+        stats
+    }
+
+    fn merge_reporting(
+        &mut self,
+        other: Self,
+        mut on_progress: impl FnMut(usize, usize),
+        stats: &mut MergeStats,
+    ) {
+        // Removes don't advance the map clock, so a clock that dominates the
+        // other map's clock does not mean we've seen all of its removes (nor
+        // the other way around). Merging with a pristine map is the only
+        // shortcut that is always safe.
+        let total = self.entries.len() + other.entries.len();
+        if other.is_default() {
+            on_progress(total, total);
+            return;
+        }
+        if self.is_default() {
+            stats.added += other.entries.len();
+            stats.deferred += other.deferred.len();
+            let tombstones = self.tombstones.take();
+            let deferred_limit = self.deferred_limit.take();
+            let capacity = self.capacity.take();
+            *self = Self {
+                tombstones,
+                deferred_limit,
+                capacity,
+                ..other
+            };
+            if let Some(bound) = self.deferred_limit.as_mut() {
+                bound.order = self.deferred.keys().cloned().collect();
+                self.enforce_deferred_limit();
+            }
+            self.enforce_capacity();
+            on_progress(total, total);
+            return;
+        }
+
+        let mut processed = 0;
+        let mut tick = |processed: &mut usize| {
+            *processed += 1;
+            if (*processed).is_multiple_of(MERGE_PROGRESS_INTERVAL) {
+                on_progress(*processed, total);
+            }
+        };
+
+        // Both entry maps are sorted by key, so walk them side by side and
+        // decide each key exactly once. The survivors come out in key order,
+        // which lets `BTreeMap::from_iter` bulk-build the result instead of
+        // paying for a point insert (or remove) per key.
+        let mut keep = Vec::with_capacity(total);
+        let mut ours = mem::take(&mut self.entries).into_iter().peekable();
+        let mut theirs = other.entries.into_iter().peekable();
+        loop {
+            let order = match (ours.peek(), theirs.peek()) {
+                (Some((a, _)), Some((b, _))) => a.cmp(b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+            match order {
+                Ordering::Less => {
+                    tick(&mut processed);
+                    let (key, mut entry) = ours.next().unwrap();
+                    // other doesn't contain this entry because it:
+                    //  1. has seen it and dropped it
+                    //  2. hasn't seen it
+                    if other.clock >= entry.clock {
+                        // other has seen this entry and dropped it
+                        stats.dropped += 1;
+                    } else {
+                        stats.kept += 1;
+                        // the other map has not seen this version of this
+                        // entry, so add it. But first, we have to remove any
+                        // information that may have been known at some point
+                        // by the other map about this key and was removed.
+                        entry.clock.reset_remove(&other.clock);
+                        let mut removed_information = other.clock.clone();
+                        removed_information.reset_remove(&entry.clock);
+                        entry.val.reset_remove(&removed_information);
+                        keep.push((key, entry));
+                    }
+                }
+                Ordering::Greater => {
+                    tick(&mut processed);
+                    let (key, mut entry) = theirs.next().unwrap();
+                    // we don't have this entry, is it because we:
+                    //  1. have seen it and dropped it
+                    //  2. have not seen it
+                    if self.clock >= entry.clock {
+                        // We've seen this entry and dropped it, we won't add it back
+                        stats.dropped += 1;
+                    } else {
+                        stats.added += 1;
+                        // We have not seen this version of this entry, so we add it.
+                        // but first, we have to remove the information on this entry
+                        // that we have seen and deleted
+                        entry.clock.reset_remove(&self.clock);
+
+                        let mut information_we_deleted = self.clock.clone();
+                        information_we_deleted.reset_remove(&entry.clock);
+                        entry.val.reset_remove(&information_we_deleted);
+                        keep.push((key, entry));
+                    }
+                }
+                Ordering::Equal => {
+                    tick(&mut processed);
+                    tick(&mut processed);
+                    let (key, mut our_entry) = ours.next().unwrap();
+                    let (_, entry) = theirs.next().unwrap();
+                    if entry.clock == our_entry.clock {
+                        // both maps hold the same version of this entry, so
+                        // neither removed it. A remove of older nested dots
+                        // can still have changed one of the values without
+                        // touching the entry clock, so the values are merged.
+                        stats.unchanged += 1;
+                        our_entry.val.merge(entry.val);
+                        keep.push((key, our_entry));
+                        continue;
+                    }
+                    // SUBTLE: this entry is present in both maps, BUT that doesn't mean we
+                    // shouldn't drop it!
+                    // Perfectly possible that an item in both sets should be dropped
+                    let mut common = VClock::intersection(&entry.clock, &our_entry.clock);
+                    common.merge(entry.clock.clone_without(&self.clock));
+                    common.merge(our_entry.clock.clone_without(&other.clock));
+                    if common.is_empty() {
+                        // both maps had seen each others entry and removed them
+                        stats.dropped += 1;
+                    } else {
+                        stats.merged += 1;
+                        // we should not drop, as there is information still tracked in
+                        // the common clock.
+                        our_entry.val.merge(entry.val);
+
+                        let mut information_that_was_deleted = entry.clock.clone();
+                        information_that_was_deleted.merge(our_entry.clock.clone());
+                        information_that_was_deleted.reset_remove(&common);
+                        our_entry.val.reset_remove(&information_that_was_deleted);
+                        our_entry.clock = common;
+                        keep.push((key, our_entry));
+                    }
+                }
+            }
+        }
+        self.entries = BTreeMap::from_iter(keep);
+
+        // merge deferred removals
+        stats.deferred += other.deferred.len();
+        for (rm_clock, keys) in other.deferred {
+            self.apply_keyset_rm(keys, rm_clock);
+        }
+
+        self.clock.merge(other.clock);
+
+        self.apply_deferred();
+        self.enforce_capacity();
+        on_progress(total, total);
+    }
+
+    /// Build the payload a peer needs to catch up with this map.
+    ///
+    /// `peer_clock` is the peer's current map clock (e.g. the `add_clock` of
+    /// its `read_ctx()`). Every entry is shipped whole, even those whose
+    /// clock is dominated by the peer's clock: a remove whose context covers
+    /// only older nested dots changes the value but not the entry clock, and
+    /// removes don't advance the map clock, so the peer's clock can't tell
+    /// us it holds the same value. Keys the peer has seen and we dropped are
+    /// left out, the payload's clock tells the peer to drop them too.
+    ///
+    /// One anti-entropy round between two peers is: each peer sends its
+    /// clock, replies with the `sync_payload` built against the clock it
+    /// received, and `sync_apply`s the payload it gets back.
+    pub fn sync_payload(&self, _peer_clock: &VClock<A>) -> SyncPayload<K, V, A> {
+        SyncPayload {
+            clock: self.clock.clone(),
+            entries: self.entries.clone(),
+            deferred: self.deferred.clone(),
+        }
+    }
+
+    /// Merge a payload built by a peer with `sync_payload`.
+    ///
+    /// The payload must have been built against a clock this map has seen,
+    /// the result is then the same as merging the peer's full state.
+    pub fn sync_apply(&mut self, payload: SyncPayload<K, V, A>) {
+        let SyncPayload {
+            clock,
+            entries,
+            deferred,
+        } = payload;
+
+        self.merge(Map {
+            clock,
+            entries,
+            deferred,
+            tombstones: None,
+            deferred_limit: None,
+            capacity: None,
+        });
+    }
+
+    /// Returns the result of merging `other` into this map, leaving both
+    /// maps untouched.
+    pub fn merged_with(&self, other: &Self) -> Self {
+        let mut merged = self.clone();
+        merged.merge(other.clone());
+        merged
+    }
+
+    /// Panics if merging `a` and `b` depends on the order they're merged in.
+    ///
+    /// Meant for checking that a nested CRDT composes correctly in a `Map`
+    /// from downstream tests, available with the `testing` feature.
+    #[cfg(feature = "testing")]
+    pub fn assert_merge_commutative(a: &Self, b: &Self)
+    where
+        V: PartialEq,
+    {
+        assert_eq!(
+            a.merged_with(b),
+            b.merged_with(a),
+            "merge is not commutative"
+        );
+    }
+
+    /// Panics if merging `a`, `b` and `c` depends on how the merges are
+    /// grouped, see `assert_merge_commutative`.
+    #[cfg(feature = "testing")]
+    pub fn assert_merge_associative(a: &Self, b: &Self, c: &Self)
+    where
+        V: PartialEq,
+    {
+        assert_eq!(
+            a.merged_with(b).merged_with(c),
+            a.merged_with(&b.merged_with(c)),
+            "merge is not associative"
+        );
+    }
+
+    /// Apply `ops` in an order shuffled by `seed`, then panic if the result
+    /// differs from applying them in the order given.
+    ///
+    /// The ops of each actor keep their relative order, as op based
+    /// replication requires, while the actors' runs are interleaved at
+    /// random. Removes don't belong to an actor and may move anywhere.
+    /// Meant for fuzzing convergence from downstream tests, available with
+    /// the `testing` feature.
+    #[cfg(feature = "testing")]
+    pub fn apply_shuffled(&mut self, ops: Vec<Op<K, V, A>>, seed: u64)
+    where
+        V: PartialEq,
+        Op<K, V, A>: Clone,
+    {
+        let mut in_order = self.clone();
+        let mut runs: BTreeMap<&A, VecDeque<Op<K, V, A>>> = BTreeMap::new();
+        let mut queues = Vec::new();
+        for op in ops.iter() {
+            in_order.apply(op.clone());
+            match op {
+                Op::Up { dot, .. } | Op::UpMany { dot, .. } => {
+                    runs.entry(&dot.actor).or_default().push_back(op.clone())
+                }
+                Op::Rm { .. } => queues.push(VecDeque::from([op.clone()])),
+            }
+        }
+        queues.extend(runs.into_values());
+
+        // splitmix64, a tiny seedable generator is all a shuffle needs
+        let mut state = seed;
+        let mut next_random = || {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        while !queues.is_empty() {
+            let pick = (next_random() % queues.len() as u64) as usize;
+            if let Some(op) = queues[pick].pop_front() {
+                self.apply(op);
+            }
+            if queues[pick].is_empty() {
+                queues.swap_remove(pick);
+            }
+        }
+
+        assert_eq!(
+            *self, in_order,
+            "applying the ops out of order changed the result"
+        );
+    }
+
+    /// Merge with `other`, but when both maps edited an entry concurrently
+    /// keep only one side's value instead of merging the two values.
+    ///
+    /// The value kept is the one whose entry clock has the greater highest
+    /// dot, ties are broken by the actor of that dot and then by the rest
+    /// of the dots in the same way. The losing side's edits to the value are
+    /// discarded even though its entry clock is still merged in, so this is
+    /// NOT a CRDT merge of the values: prefer `merge` unless you explicitly
+    /// want last-writer-wins conflict resolution.
+    pub fn merge_lww(&mut self, other: &Self) {
+        let mut other = other.clone();
+        for (key, their_entry) in other.entries.iter_mut() {
+            if let Some(our_entry) = self.entries.get_mut(key) {
+                if our_entry.clock.concurrent(&their_entry.clock) {
+                    if Self::lww_rank(&our_entry.clock) > Self::lww_rank(&their_entry.clock) {
+                        their_entry.val = our_entry.val.clone();
+                    } else {
+                        our_entry.val = their_entry.val.clone();
+                    }
+                }
+            }
+        }
+        self.merge(other);
+    }
+
+    /// The dots of a clock ordered from the highest (counter, actor) down.
+    fn lww_rank(clock: &VClock<A>) -> Vec<(u64, &A)> {
+        let mut rank: Vec<_> = clock.iter().map(|dot| (dot.counter, dot.actor)).collect();
+        rank.sort_by(|a, b| b.cmp(a));
+        rank
+    }
+}
+
+#[cfg(feature = "num")]
+impl<K: Ord, A: Ord + Hash + Clone + Debug> Map<K, GCounter<A>, A> {
+    /// Returns the sum of the counters in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// use crdts::{CmRDT, GCounter, Map};
+    ///
+    /// let mut hits: Map<&str, GCounter<u8>, u8> = Map::new();
+    /// for (page, actor) in [("home", 1), ("home", 2), ("about", 1)] {
+    ///     let ctx = hits.read_ctx().derive_add_ctx(actor);
+    ///     hits.apply(hits.update(page, ctx, |c, ctx| c.inc(ctx.dot.actor)));
+    /// }
+    ///
+    /// assert_eq!(hits.sum_counters(), 3u8.into());
+    /// assert_eq!(hits.max_counter_value(), Some(2u8.into()));
+    /// ```
+    pub fn sum_counters(&self) -> BigUint {
+        self.entries.values().map(|entry| entry.val.read()).sum()
+    }
+
+    /// Returns the value of the largest counter in the map, or `None` if
+    /// the map is empty.
+    pub fn max_counter_value(&self) -> Option<BigUint> {
+        self.entries.values().map(|entry| entry.val.read()).max()
+    }
+}
+
+impl<K: Ord, M: Hash + Eq + Clone, A: Ord + Hash + Clone + Debug> Map<K, Orswot<M, A>, A> {
+    /// Build the op that adds `member` to the set under `key`.
+    ///
+    /// # Examples
+    /// ```
+    /// use crdts::{CmRDT, Map, Orswot};
+    ///
+    /// let mut tags: Map<&str, Orswot<&str, u8>, u8> = Map::new();
+    /// let ctx = tags.read_ctx().derive_add_ctx(1);
+    /// tags.apply(tags.add_to_set("post-1", "rust", ctx));
+    /// assert!(tags.get(&"post-1").val.unwrap().contains(&"rust").val);
+    ///
+    /// let ctx = tags.read_ctx().derive_add_ctx(1);
+    /// tags.apply(tags.rm_from_set("post-1", "rust", ctx));
+    /// assert!(!tags.get(&"post-1").val.unwrap().contains(&"rust").val);
+    /// ```
+    pub fn add_to_set(
+        &self,
+        key: impl Into<K>,
+        member: M,
+        ctx: AddCtx<A>,
+    ) -> Op<K, Orswot<M, A>, A> {
+        self.update(key, ctx, |set, ctx| set.add(member, ctx))
+    }
+
+    /// Build the op that removes `member` from the set under `key`, as
+    /// seen by this map.
+    ///
+    /// Like any update, this takes an add context for the map's entry. The
+    /// remove context for the member is derived from the set we hold.
+    pub fn rm_from_set(
+        &self,
+        key: impl Into<K>,
+        member: M,
+        ctx: AddCtx<A>,
+    ) -> Op<K, Orswot<M, A>, A> {
+        self.update(key, ctx, |set, _| {
+            let rm_ctx = set.contains(&member).derive_rm_ctx();
+            set.rm(member, rm_ctx)
+        })
+    }
+}
+
+impl<K: Ord + Clone, T: Clone + PartialEq, A: Ord + Hash + Clone> Map<K, Register<T, u64, A>, A> {
+    /// Build remove ops for the entries last written more than `ttl` before
+    /// `now`, using the marker each register reads as the time of its last
+    /// write.
+    ///
+    /// The map has no notion of time, so the caller supplies it and is
+    /// expected to write timestamps in the same unit as markers. The ops are
+    /// regular removes of the entries as this map has seen them: a write to
+    /// an entry concurrent with its expiry survives it, as with any remove.
+    ///
+    /// # Examples
+    /// ```
+    /// use crdts::lwwmap::Register;
+    /// use crdts::{CmRDT, Map};
+    ///
+    /// let mut sessions: Map<&str, Register<&str, u64, u8>, u8> = Map::new();
+    /// for (key, written_at) in [("alice", 10), ("bob", 50)] {
+    ///     let ctx = sessions.read_ctx().derive_add_ctx(1);
+    ///     sessions.apply(sessions.update(key, ctx, |reg, ctx| reg.write("token", written_at, ctx)));
+    /// }
+    ///
+    /// for op in sessions.expire(100, 60) {
+    ///     sessions.apply(op);
+    /// }
+    /// assert_eq!(sessions.keys().map(|k| *k.val).collect::<Vec<_>>(), vec!["bob"]);
+    /// ```
+    pub fn expire(&self, now: u64, ttl: u64) -> Vec<Op<K, Register<T, u64, A>, A>> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| {
+                entry
+                    .val
+                    .read()
+                    .is_some_and(|reg| now.saturating_sub(reg.marker) > ttl)
+            })
+            .map(|(key, entry)| {
+                self.rm(
+                    key.clone(),
+                    RmCtx {
+                        clock: entry.clock.clone(),
+                    },
+                )
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -595,6 +2920,64 @@ mod test {
         assert_eq!(m.get(&0).val, Some(Map::new()));
     }
 
+    #[test]
+    fn test_check_invariants_detects_corrupt_state() {
+        let mut m: TestMap = Map::new();
+        m.apply(m.update(0, m.read_ctx().derive_add_ctx(1), |inner, ctx| {
+            inner.update(0, ctx, |reg, ctx| reg.write(0, ctx))
+        }));
+        assert_eq!(m.check_invariants(), Ok(()));
+
+        let mut ahead = m.clone();
+        ahead
+            .entries
+            .get_mut(&0)
+            .unwrap()
+            .clock
+            .apply(Dot::new(2, 1));
+        assert_eq!(
+            ahead.check_invariants(),
+            Err(InvariantError::EntryAheadOfClock {
+                key: 0,
+                entry_clock: VClock::from_pairs(vec![(1, 1), (2, 1)]),
+                clock: Dot::new(1, 1).into(),
+            })
+        );
+
+        let mut stale = m.clone();
+        let rm_clock = VClock::from(Dot::new(1, 1));
+        stale
+            .deferred
+            .insert(rm_clock.clone(), vec![0].into_iter().collect());
+        assert_eq!(
+            stale.check_invariants(),
+            Err(InvariantError::StaleDeferred {
+                rm_clock,
+                clock: Dot::new(1, 1).into(),
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "map invariants violated")]
+    fn test_merge_checks_invariants_in_debug_builds() {
+        let mut m: TestMap = Map::new();
+        m.apply(m.update(0, m.read_ctx().derive_add_ctx(1), |inner, ctx| {
+            inner.update(0, ctx, |reg, ctx| reg.write(0, ctx))
+        }));
+        // an entry with a dot its map clock has never seen
+        let mut corrupt = m.clone();
+        corrupt.entries.insert(
+            1,
+            Entry {
+                clock: Dot::new(2, 1).into(),
+                val: Map::default(),
+            },
+        );
+        m.merge(corrupt);
+    }
+
     #[test]
     fn test_op_exchange_converges_quickcheck1() {
         let op_actor1 = Op::Up {
@@ -656,6 +3039,9 @@ mod test {
             clock: VClock::from(Dot::new(75, 1)),
             entries: BTreeMap::new(),
             deferred: HashMap::new(),
+            tombstones: None,
+            deferred_limit: None,
+            capacity: None,
         };
 
         let mut m2: Map<u8, Orswot<u8, u8>, u8> = Map {
@@ -679,6 +3065,9 @@ mod test {
             .into_iter()
             .collect(),
             deferred: HashMap::new(),
+            tombstones: None,
+            deferred_limit: None,
+            capacity: None,
         };
 
         m1.merge(m2.clone());
@@ -702,7 +3091,10 @@ mod test {
                 )]
                 .into_iter()
                 .collect(),
-                deferred: HashMap::new()
+                deferred: HashMap::new(),
+                tombstones: None,
+                deferred_limit: None,
+                capacity: None,
             }
         );
 
@@ -157,4 +157,25 @@ mod test {
 
         assert_eq!(b.val, 6);
     }
+    #[test]
+    fn test_merge_conflicting_writes() {
+        // Three replicas start from the same value and write concurrently
+        let base = MaxReg { val: 1 };
+        let mut a = base.clone();
+        let mut b = base.clone();
+        let mut c = base;
+        a.apply(a.write(7));
+        b.apply(b.write(3));
+        c.apply(c.write(5));
+
+        // EXPECTED: the max wins no matter the order the replicas merge in
+        let mut abc = a.clone();
+        abc.merge(b.clone());
+        abc.merge(c.clone());
+        let mut cba = c;
+        cba.merge(b);
+        cba.merge(a);
+        assert_eq!(abc, MaxReg { val: 7 });
+        assert_eq!(cba, abc);
+    }
 }
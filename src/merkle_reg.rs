@@ -101,7 +101,7 @@ impl<T: SerDe> MerkleReg<T> {
     }
 
     /// Read the current values held by the register
-    pub fn read(&self) -> Content<T> {
+    pub fn read(&self) -> Content<'_, T> {
         Content {
             nodes: self
                 .roots
@@ -131,7 +131,7 @@ impl<T: SerDe> MerkleReg<T> {
     }
 
     /// Returns the children of a node
-    pub fn children(&self, hash: Hash) -> Content<T> {
+    pub fn children(&self, hash: Hash) -> Content<'_, T> {
         let nodes = self.dag.get(&hash).map(|node| {
             node.children
                 .iter()
@@ -146,7 +146,7 @@ impl<T: SerDe> MerkleReg<T> {
     }
 
     /// Returns the parents of a node
-    pub fn parents(&self, hash: Hash) -> Content<T> {
+    pub fn parents(&self, hash: Hash) -> Content<'_, T> {
         let parents = self
             .dag
             .iter()
@@ -6,7 +6,9 @@ use core::mem;
 use serde::{Deserialize, Serialize};
 
 use crate::ctx::{AddCtx, ReadCtx};
-use crate::{CmRDT, CvRDT, ResetRemove, VClock};
+use crate::{
+    CausalContext, CmRDT, Concurrency, CvRDT, DotStore, HeapSize, LogicalEq, ResetRemove, VClock,
+};
 
 /// MVReg (Multi-Value Register)
 /// On concurrent writes, we will keep all values for which
@@ -26,8 +28,9 @@ use crate::{CmRDT, CvRDT, ResetRemove, VClock};
 ///
 /// r1.apply(op); // we replicate op to r1
 ///
-/// // Since "bob" and "alice" were added concurrently, we see both on read
-/// assert_eq!(r1.read().val, vec!["bob", "alice"]);
+/// // Since "bob" and "alice" were added concurrently, we see both on read,
+/// // ordered by the actor that wrote them
+/// assert_eq!(r1.read().val, vec!["alice", "bob"]);
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(transparent)]
@@ -47,6 +50,15 @@ pub enum Op<V, A: Ord> {
     },
 }
 
+/// The concurrent values that changed in a merge, see `MVReg::merge_tracking`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegMergeReport<V> {
+    /// Values the merge brought in
+    pub added: Vec<V>,
+    /// Values the merge superseded
+    pub dropped: Vec<V>,
+}
+
 impl<V: Display, A: Ord + Display> Display for MVReg<V, A> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "|")?;
@@ -86,8 +98,18 @@ impl<V: PartialEq, A: Ord> PartialEq for MVReg<V, A> {
 
 impl<V: Eq, A: Ord> Eq for MVReg<V, A> {}
 
+impl<V, A: Ord> Concurrency for MVReg<V, A> {
+    fn concurrency(&self) -> usize {
+        self.vals.len()
+    }
+}
+
 impl<V, A: Ord> ResetRemove<A> for MVReg<V, A> {
     fn reset_remove(&mut self, clock: &VClock<A>) {
+        if clock.is_empty() {
+            return;
+        }
+
         self.vals = mem::take(&mut self.vals)
             .into_iter()
             .filter_map(|(mut val_clock, val)| {
@@ -102,6 +124,53 @@ impl<V, A: Ord> ResetRemove<A> for MVReg<V, A> {
     }
 }
 
+impl<V, A: Ord + Clone + Debug> DotStore<A> for MVReg<V, A> {}
+
+impl<V: PartialEq, A: Ord> LogicalEq for MVReg<V, A> {
+    /// Returns true if both registers hold the same concurrent values,
+    /// ignoring the clocks the values were written with.
+    fn logically_eq(&self, other: &Self) -> bool {
+        let count =
+            |vals: &[(VClock<A>, V)], val: &V| vals.iter().filter(|(_, v)| v == val).count();
+        self.vals.len() == other.vals.len()
+            && self
+                .vals
+                .iter()
+                .all(|(_, val)| count(&self.vals, val) == count(&other.vals, val))
+    }
+}
+
+impl<V, A: Ord> HeapSize for MVReg<V, A> {
+    fn heap_size(&self) -> usize {
+        self.vals.capacity() * mem::size_of::<(VClock<A>, V)>()
+            + self
+                .vals
+                .iter()
+                .map(|(clock, _)| clock.heap_size())
+                .sum::<usize>()
+    }
+}
+
+impl<V, A: Ord + Clone + Debug> CausalContext<A> for MVReg<V, A> {
+    fn causal_context(&self) -> VClock<A> {
+        self.clock()
+    }
+}
+
+/// Renders the concurrent values as a JSON array in `read` order.
+#[cfg(feature = "json")]
+impl<V: Serialize, A: Ord> crate::json::ToValue for MVReg<V, A> {
+    fn to_value(&self) -> crate::json::Value {
+        let mut vals: Vec<_> = self.vals.iter().collect();
+        vals.sort_by(|(a, _), (b, _)| a.syntactic_cmp(b));
+        crate::json::Value::Array(
+            vals.into_iter()
+                .map(|(_, val)| crate::json::plain(val))
+                .collect(),
+        )
+    }
+}
+
 impl<V, A: Ord> Default for MVReg<V, A> {
     fn default() -> Self {
         Self { vals: Vec::new() }
@@ -182,7 +251,30 @@ impl<V, A: Ord + Clone + Debug> MVReg<V, A> {
         Default::default()
     }
 
+    /// Returns true if the register has never been written to (or every
+    /// write has since been reset-removed), i.e. it holds no values and
+    /// its clock is empty.
+    pub fn is_default(&self) -> bool {
+        self.vals.is_empty()
+    }
+
     /// Set the value of the register
+    ///
+    /// The op's clock is taken from `ctx`, so always derive the context from
+    /// a read of this register: that clock is what lets the write replace
+    /// exactly the values you've seen while keeping concurrent ones.
+    ///
+    /// ``` rust
+    /// use crdts::{CmRDT, MVReg};
+    /// let mut reg = MVReg::new();
+    /// let ctx = reg.read_ctx().derive_add_ctx("A");
+    /// reg.apply(reg.write("hello", ctx));
+    /// assert_eq!(reg.read().val, vec!["hello"]);
+    ///
+    /// let ctx = reg.read_ctx().derive_add_ctx("A");
+    /// reg.apply(reg.write("world", ctx));
+    /// assert_eq!(reg.read().val, vec!["world"]);
+    /// ```
     pub fn write(&self, val: V, ctx: AddCtx<A>) -> Op<V, A> {
         Op::Put {
             clock: ctx.clock,
@@ -191,12 +283,21 @@ impl<V, A: Ord + Clone + Debug> MVReg<V, A> {
     }
 
     /// Consumes the register and returns the values
+    ///
+    /// Concurrent values are ordered by their clocks, comparing the clocks'
+    /// (actor, counter) pairs in turn, so every replica holding the same
+    /// values reads them in the same order no matter how they were merged.
     pub fn read(&self) -> ReadCtx<Vec<V>, A>
     where
         V: Clone,
     {
         let clock = self.clock();
-        let concurrent_vals = self.vals.iter().cloned().map(|(_, v)| v).collect();
+        let mut concurrent_vals: Vec<_> = self.vals.iter().collect();
+        concurrent_vals.sort_by(|(a, _), (b, _)| a.syntactic_cmp(b));
+        let concurrent_vals = concurrent_vals
+            .into_iter()
+            .map(|(_, v)| v.clone())
+            .collect();
 
         ReadCtx {
             add_clock: clock.clone(),
@@ -215,8 +316,78 @@ impl<V, A: Ord + Clone + Debug> MVReg<V, A> {
         }
     }
 
+    /// Compares the clocks of two registers: `Some(Greater)` or `Some(Equal)`
+    /// means this register has seen every write the other has seen, `None`
+    /// means both saw writes the other didn't.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::cmp::Ordering;
+    /// use crdts::{CmRDT, MVReg};
+    ///
+    /// let mut a = MVReg::new();
+    /// a.apply(a.write("x", a.read_ctx().derive_add_ctx("A")));
+    /// let mut b = a.clone();
+    /// b.apply(b.write("y", b.read_ctx().derive_add_ctx("B")));
+    ///
+    /// assert_eq!(b.causal_cmp(&a), Some(Ordering::Greater));
+    /// a.apply(a.write("z", a.read_ctx().derive_add_ctx("A")));
+    /// assert_eq!(b.causal_cmp(&a), None);
+    /// ```
+    pub fn causal_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.clock().partial_cmp(&other.clock())
+    }
+
+    /// Merge `other` into this register, returning the values that appeared
+    /// and disappeared as a result. Both lists are in `read` order.
+    ///
+    /// The merge itself is the same as `CvRDT::merge`.
+    pub fn merge_tracking(&mut self, other: Self) -> RegMergeReport<V>
+    where
+        V: Clone,
+    {
+        let mut before = self.vals.clone();
+        self.merge(other);
+
+        let mut added: Vec<_> = self
+            .vals
+            .iter()
+            .filter(|(clock, _)| before.iter().all(|(c, _)| c != clock))
+            .cloned()
+            .collect();
+        before.retain(|(clock, _)| self.vals.iter().all(|(c, _)| c != clock));
+
+        added.sort_by(|(a, _), (b, _)| a.syntactic_cmp(b));
+        before.sort_by(|(a, _), (b, _)| a.syntactic_cmp(b));
+        RegMergeReport {
+            added: added.into_iter().map(|(_, v)| v).collect(),
+            dropped: before.into_iter().map(|(_, v)| v).collect(),
+        }
+    }
+
+    /// Forget the parts of the values' clocks covered by a causally stable
+    /// clock, keeping every value.
+    ///
+    /// `stable` must only cover events every replica has seen, e.g. the
+    /// `stable_clock` of a `vclock::StabilityTracker`, and ops covered by it
+    /// must no longer be delivered: a replayed op would no longer be
+    /// recognised as stale. Unlike `reset_remove`, which drops the values
+    /// whose clocks are covered, the live values stay readable.
+    ///
+    /// The latest counter of each actor is kept wherever it appears, so the
+    /// register's `clock` is unchanged: new writes must keep getting dots
+    /// that replicas which have not collected yet haven't seen.
+    pub fn gc(&mut self, stable: &VClock<A>) {
+        let clock = self.clock();
+        for (val_clock, _) in self.vals.iter_mut() {
+            val_clock.dots.retain(|actor, counter| {
+                *counter > stable.get(actor) || *counter == clock.get(actor)
+            });
+        }
+    }
+
     /// A clock with latest versions of all actors operating on this register
-    fn clock(&self) -> VClock<A> {
+    pub fn clock(&self) -> VClock<A> {
         self.vals
             .iter()
             .fold(VClock::new(), |mut accum_clock, (c, _)| {
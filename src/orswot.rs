@@ -1,6 +1,6 @@
 /// Observed-Remove Set With Out Tombstones (ORSWOT), ported directly from `riak_dt`.
 use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt::{Debug, Display};
 use std::hash::Hash;
 use std::mem;
@@ -8,10 +8,18 @@ use std::mem;
 use serde::{Deserialize, Serialize};
 
 use crate::ctx::{AddCtx, ReadCtx, RmCtx};
-use crate::{CmRDT, CvRDT, Dot, ResetRemove, VClock};
+use crate::{
+    CausalContext, CmRDT, Concurrency, CvRDT, Dot, DotStore, HeapSize, LogicalEq, ResetRemove,
+    VClock,
+};
 
 /// `Orswot` is an add-biased or-set without tombstones ported from
 /// the riak_dt CRDT library.
+///
+/// Add-biased means adds win over concurrent removes: a remove only drops
+/// the dots of the adds its context has seen, so a member added concurrently
+/// with its removal is still in the set once both replicas have merged, in
+/// whichever order they merge.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Orswot<M: Hash + Eq, A: Ord + Hash> {
     pub(crate) clock: VClock<A>,
@@ -41,6 +49,65 @@ pub enum Op<M, A: Ord> {
     },
 }
 
+/// Where a member stands in an `Orswot`, see `Orswot::explain`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Presence {
+    /// The member has dots no remove has covered, it is in the set.
+    Live,
+    /// A remove naming the member is waiting for us to see the adds it
+    /// covers. The member may be live now, but any of its dots covered by
+    /// the pending removes will be dropped once those adds arrive.
+    Deferred,
+    /// Every dot of the member has been removed, or it was never added.
+    Dropped,
+}
+
+/// A snapshot of the state deciding whether a member is in an `Orswot`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresenceExplanation<A: Ord> {
+    /// Where the member stands
+    pub presence: Presence,
+    /// The dots keeping the member alive, empty if it is not in the set
+    pub member_clock: VClock<A>,
+    /// The clock of the whole set
+    pub set_clock: VClock<A>,
+    /// The clocks of the deferred removes that name the member
+    pub pending_removes: Vec<VClock<A>>,
+}
+
+impl<A: Ord + Display> Display for PresenceExplanation<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.presence {
+            Presence::Live => write!(
+                f,
+                "live: its dots {} are not covered by any remove",
+                self.member_clock
+            )?,
+            Presence::Deferred => write!(
+                f,
+                "deferred: {} pending remove(s) name it but cover adds not yet seen by {}",
+                self.pending_removes.len(),
+                self.set_clock
+            )?,
+            Presence::Dropped => {
+                write!(f, "dropped: no dots remain for it under {}", self.set_clock)?
+            }
+        }
+        for clock in self.pending_removes.iter() {
+            write!(f, "\n  pending remove {}", clock)?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders the members as a JSON array, see `crate::json`.
+#[cfg(feature = "json")]
+impl<M: Hash + Eq + Serialize, A: Ord + Hash> crate::json::ToValue for Orswot<M, A> {
+    fn to_value(&self) -> crate::json::Value {
+        crate::json::sorted_array(self.entries.keys())
+    }
+}
+
 impl<M: Hash + Eq, A: Ord + Hash> Default for Orswot<M, A> {
     fn default() -> Self {
         Orswot {
@@ -66,7 +133,8 @@ impl<M: Hash + Clone + Eq, A: Ord + Hash + Clone + Debug> CmRDT for Orswot<M, A>
         match op {
             Op::Add { dot, members } => {
                 if self.clock.get(&dot.actor) >= dot.counter {
-                    // we've already seen this op
+                    // we've already seen this op, re-delivered ops must not
+                    // add their members back or bump the clock again
                     return;
                 }
 
@@ -165,7 +233,7 @@ impl<M: Hash + Eq + Clone + Debug, A: Ord + Hash + Clone + Debug> CvRDT for Orsw
                 common.merge(our_clock.clone_without(&other.clock));
                 if common.is_empty() {
                     // both maps had seen each others entry and removed them
-                    self.entries.remove(&entry).unwrap();
+                    self.entries.remove(&entry);
                 } else {
                     // we should not drop, as there is information still tracked in
                     // the common clock.
@@ -198,8 +266,15 @@ impl<M: Hash + Eq + Clone + Debug, A: Ord + Hash + Clone + Debug> CvRDT for Orsw
     }
 }
 
+impl<M: Hash + Eq, A: Ord + Hash> Concurrency for Orswot<M, A> {}
+
 impl<M: Hash + Clone + Eq, A: Ord + Hash> ResetRemove<A> for Orswot<M, A> {
     fn reset_remove(&mut self, clock: &VClock<A>) {
+        if clock.is_empty() {
+            // nothing to forget, avoid rebuilding the entries and deferred maps
+            return;
+        }
+
         self.clock.reset_remove(clock);
 
         self.entries = mem::take(&mut self.entries)
@@ -228,17 +303,107 @@ impl<M: Hash + Clone + Eq, A: Ord + Hash> ResetRemove<A> for Orswot<M, A> {
     }
 }
 
+impl<M: Hash + Eq + Clone + Debug, A: Ord + Hash + Clone + Debug> DotStore<A> for Orswot<M, A> {}
+
+impl<M: Hash + Eq, A: Ord + Hash> LogicalEq for Orswot<M, A> {
+    /// Returns true if both sets hold the same members, ignoring the clocks
+    /// and deferred removes that `PartialEq` also compares.
+    fn logically_eq(&self, other: &Self) -> bool {
+        self.entries.len() == other.entries.len()
+            && self
+                .entries
+                .keys()
+                .all(|member| other.entries.contains_key(member))
+    }
+}
+
+impl<M: Hash + Eq, A: Ord + Hash> HeapSize for Orswot<M, A> {
+    fn heap_size(&self) -> usize {
+        let entries = self.entries.capacity() * mem::size_of::<(M, VClock<A>)>()
+            + self
+                .entries
+                .values()
+                .map(|clock| clock.heap_size())
+                .sum::<usize>();
+        let deferred = self.deferred.capacity() * mem::size_of::<(VClock<A>, HashSet<M>)>()
+            + self
+                .deferred
+                .iter()
+                .map(|(clock, members)| {
+                    clock.heap_size() + members.capacity() * mem::size_of::<M>()
+                })
+                .sum::<usize>();
+        self.clock.heap_size() + entries + deferred
+    }
+}
+
+impl<M: Hash + Clone + Eq, A: Ord + Hash + Clone> CausalContext<A> for Orswot<M, A> {
+    fn causal_context(&self) -> VClock<A> {
+        self.clock.clone()
+    }
+}
+
 impl<M: Hash + Clone + Eq, A: Ord + Hash + Clone> Orswot<M, A> {
     /// Returns a new `Orswot` instance.
     pub fn new() -> Self {
         Default::default()
     }
 
+    /// Constructs an Orswot seeded with the given members, each member is
+    /// added under a fresh dot from `actor` so the resulting set's clock
+    /// dominates every member. Duplicate members are only added once.
+    pub fn from_reads(actor: A, members: impl IntoIterator<Item = M>) -> Self {
+        let mut set = Self::new();
+        for member in members {
+            if set.entries.contains_key(&member) {
+                continue;
+            }
+            let dot = set.clock.inc(actor.clone());
+            set.clock.apply_dot(dot.clone());
+            let mut clock = VClock::new();
+            clock.apply_dot(dot);
+            set.entries.insert(member, clock);
+        }
+        set
+    }
+
+    /// Consumes the set and returns its members.
+    pub fn into_set(self) -> BTreeSet<M>
+    where
+        M: Ord,
+    {
+        self.entries.into_keys().collect()
+    }
+
     /// Return a snapshot of the ORSWOT clock
     pub fn clock(&self) -> VClock<A> {
         self.clock.clone()
     }
 
+    /// Returns true if the set is causally indistinguishable from
+    /// `Orswot::default()`: no members, no deferred removes and an empty clock.
+    pub fn is_default(&self) -> bool {
+        self.clock.is_empty() && self.entries.is_empty() && self.deferred.is_empty()
+    }
+
+    /// Returns true if the set has no members, false otherwise
+    pub fn is_empty(&self) -> ReadCtx<bool, A> {
+        ReadCtx {
+            add_clock: self.clock.clone(),
+            rm_clock: self.clock.clone(),
+            val: self.entries.is_empty(),
+        }
+    }
+
+    /// Returns the number of members in the set
+    pub fn len(&self) -> ReadCtx<usize, A> {
+        ReadCtx {
+            add_clock: self.clock.clone(),
+            rm_clock: self.clock.clone(),
+            val: self.entries.len(),
+        }
+    }
+
     /// Add a single element.
     pub fn add(&self, member: M, ctx: AddCtx<A>) -> Op<M, A> {
         Op::Add {
@@ -271,6 +436,26 @@ impl<M: Hash + Clone + Eq, A: Ord + Hash + Clone> Orswot<M, A> {
         }
     }
 
+    /// Remove every current member matching `pred` with a witnessing ctx.
+    ///
+    /// The predicate is evaluated against this replica's members when the op
+    /// is generated. As with `rm_all`, adds the ctx hasn't seen survive the
+    /// remove, even if they would have matched the predicate.
+    pub fn rm_if<P: FnMut(&M) -> bool>(&self, ctx: RmCtx<A>, mut pred: P) -> Op<M, A> {
+        Op::Rm {
+            clock: ctx.clock,
+            members: self.entries.keys().filter(|m| pred(m)).cloned().collect(),
+        }
+    }
+
+    /// Locally drop every member for which `pred` returns false.
+    ///
+    /// This is not a CRDT operation, see `Map::retain` for the caveats. Use
+    /// `rm_if` to remove members from a replicated set.
+    pub fn retain<P: FnMut(&M) -> bool>(&mut self, mut pred: P) {
+        self.entries.retain(|member, _| pred(member));
+    }
+
     /// Remove members using a witnessing clock.
     fn apply_rm(&mut self, members: HashSet<M>, clock: VClock<A>) {
         for member in members.iter() {
@@ -294,7 +479,45 @@ impl<M: Hash + Clone + Eq, A: Ord + Hash + Clone> Orswot<M, A> {
         }
     }
 
+    /// Explain whether `member` is in the set and why.
+    ///
+    /// This is read only introspection meant for debugging members that
+    /// appear and disappear across merges.
+    pub fn explain(&self, member: &M) -> PresenceExplanation<A> {
+        let member_clock = self.entries.get(member).cloned().unwrap_or_default();
+        let mut pending_removes: Vec<VClock<A>> = self
+            .deferred
+            .iter()
+            .filter(|(_, members)| members.contains(member))
+            .map(|(clock, _)| clock.clone())
+            .collect();
+        pending_removes.sort_by(|a, b| a.syntactic_cmp(b));
+
+        let presence = if !pending_removes.is_empty() {
+            Presence::Deferred
+        } else if member_clock.is_empty() {
+            Presence::Dropped
+        } else {
+            Presence::Live
+        };
+
+        PresenceExplanation {
+            presence,
+            member_clock,
+            set_clock: self.clock.clone(),
+            pending_removes,
+        }
+    }
+
     /// Check if the set contains a member
+    ///
+    /// A member that was added and removed concurrently is reported as
+    /// present, see the add-wins note on `Orswot`.
+    ///
+    /// Members are keyed in a `HashMap`, so this is a single hash lookup
+    /// regardless of the size of the set. The remaining cost is cloning the
+    /// set clock and the member's clock into the returned context, which
+    /// grows with the number of actors, not members.
     pub fn contains(&self, member: &M) -> ReadCtx<bool, A> {
         let member_clock_opt = self.entries.get(member);
         let exists = member_clock_opt.is_some();
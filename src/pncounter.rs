@@ -2,7 +2,7 @@ use num::bigint::BigInt;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
-use crate::traits::{CmRDT, CvRDT, ResetRemove};
+use crate::traits::{CausalContext, CmRDT, Concurrency, CvRDT, LogicalEq, ResetRemove};
 use crate::{Dot, GCounter, VClock};
 
 /// `PNCounter` allows the counter to be both incremented and decremented
@@ -92,6 +92,8 @@ impl<A: Ord + Clone + Debug> CvRDT for PNCounter<A> {
     }
 }
 
+impl<A: Ord> Concurrency for PNCounter<A> {}
+
 impl<A: Ord> ResetRemove<A> for PNCounter<A> {
     fn reset_remove(&mut self, clock: &VClock<A>) {
         self.p.reset_remove(clock);
@@ -99,6 +101,20 @@ impl<A: Ord> ResetRemove<A> for PNCounter<A> {
     }
 }
 
+impl<A: Ord> LogicalEq for PNCounter<A> {
+    fn logically_eq(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl<A: Ord + Clone + Debug> CausalContext<A> for PNCounter<A> {
+    fn causal_context(&self) -> VClock<A> {
+        let mut clock = self.p.causal_context();
+        clock.merge(self.n.causal_context());
+        clock
+    }
+}
+
 impl<A: Ord + Clone> PNCounter<A> {
     /// Produce a new `PNCounter`.
     pub fn new() -> Self {
@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use num::bigint::BigInt;
+use num::Zero;
+use serde::{Deserialize, Serialize};
+
+use crate::pncounter::{self, PNCounter};
+use crate::{CmRDT, CvRDT};
+
+/// `PNSet` is a multiset where each member carries a `PNCounter` of how many
+/// times it is in the set.
+///
+/// Increments and decrements of a member's count made concurrently on
+/// different replicas all take effect. A member is in the set while its
+/// count is above zero.
+///
+/// # Examples
+///
+/// ```
+/// use crdts::{CmRDT, PNSet};
+///
+/// let mut cart = PNSet::new();
+/// cart.apply(cart.inc("apple", "A"));
+/// cart.apply(cart.inc("apple", "A"));
+/// cart.apply(cart.dec("apple", "A"));
+///
+/// assert_eq!(cart.count(&"apple"), 1.into());
+/// assert!(cart.contains(&"apple"));
+/// assert!(!cart.contains(&"pear"));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct PNSet<M: Ord, A: Ord> {
+    counts: BTreeMap<M, PNCounter<A>>,
+}
+
+/// An Op which is produced from changing the count of a member.
+/// Ship these ops to other replicas to have them sync up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Op<M, A: Ord> {
+    /// The member whose count changes
+    pub member: M,
+    /// The op to apply to the member's counter
+    pub op: pncounter::Op<A>,
+}
+
+impl<M: Ord, A: Ord> Default for PNSet<M, A> {
+    fn default() -> Self {
+        Self {
+            counts: BTreeMap::new(),
+        }
+    }
+}
+
+impl<M: Ord, A: Ord + Clone + Debug> CmRDT for PNSet<M, A> {
+    type Op = Op<M, A>;
+    type Validation = <PNCounter<A> as CmRDT>::Validation;
+
+    fn validate_op(&self, op: &Self::Op) -> Result<(), Self::Validation> {
+        match self.counts.get(&op.member) {
+            Some(counter) => counter.validate_op(&op.op),
+            None => PNCounter::new().validate_op(&op.op),
+        }
+    }
+
+    fn apply(&mut self, Op { member, op }: Self::Op) {
+        self.counts.entry(member).or_default().apply(op);
+    }
+}
+
+impl<M: Ord, A: Ord + Clone + Debug> CvRDT for PNSet<M, A> {
+    type Validation = <PNCounter<A> as CvRDT>::Validation;
+
+    fn validate_merge(&self, other: &Self) -> Result<(), Self::Validation> {
+        for (member, counter) in self.counts.iter() {
+            if let Some(other_counter) = other.counts.get(member) {
+                counter.validate_merge(other_counter)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (member, counter) in other.counts {
+            self.counts.entry(member).or_default().merge(counter);
+        }
+    }
+}
+
+impl<M: Ord, A: Ord + Clone> PNSet<M, A> {
+    /// Produce a new empty `PNSet`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Generate an Op to increment the count of a member.
+    pub fn inc(&self, member: M, actor: A) -> Op<M, A> {
+        self.inc_many(member, actor, 1)
+    }
+
+    /// Generate an Op to decrement the count of a member.
+    pub fn dec(&self, member: M, actor: A) -> Op<M, A> {
+        self.dec_many(member, actor, 1)
+    }
+
+    /// Generate an Op to increment the count of a member by a number of steps.
+    pub fn inc_many(&self, member: M, actor: A, steps: u64) -> Op<M, A> {
+        let op = match self.counts.get(&member) {
+            Some(counter) => counter.inc_many(actor, steps),
+            None => PNCounter::new().inc_many(actor, steps),
+        };
+        Op { member, op }
+    }
+
+    /// Generate an Op to decrement the count of a member by a number of steps.
+    pub fn dec_many(&self, member: M, actor: A, steps: u64) -> Op<M, A> {
+        let op = match self.counts.get(&member) {
+            Some(counter) => counter.dec_many(actor, steps),
+            None => PNCounter::new().dec_many(actor, steps),
+        };
+        Op { member, op }
+    }
+
+    /// Return the current count of a member, which may be negative if it was
+    /// decremented more often than incremented.
+    pub fn count(&self, member: &M) -> BigInt {
+        self.counts
+            .get(member)
+            .map(|counter| counter.read())
+            .unwrap_or_default()
+    }
+
+    /// Return true if the member's count is above zero.
+    pub fn contains(&self, member: &M) -> bool {
+        self.count(member) > BigInt::zero()
+    }
+
+    /// Return the members whose count is above zero along with their counts.
+    pub fn read(&self) -> BTreeMap<&M, BigInt> {
+        self.counts
+            .iter()
+            .map(|(member, counter)| (member, counter.read()))
+            .filter(|(_, count)| *count > BigInt::zero())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_increments_sum() {
+        let mut a = PNSet::new();
+        let mut b = PNSet::new();
+        a.apply(a.inc_many("apple", "A", 2));
+        b.apply(b.inc_many("apple", "B", 3));
+        b.apply(b.dec("apple", "B"));
+        b.apply(b.inc("pear", "B"));
+
+        a.merge(b.clone());
+        b.merge(a.clone());
+        assert_eq!(a, b);
+        assert_eq!(a.count(&"apple"), 4.into());
+        assert_eq!(a.count(&"pear"), 1.into());
+    }
+
+    #[test]
+    fn test_contains_needs_positive_count() {
+        let mut a = PNSet::new();
+        a.apply(a.dec("apple", "A"));
+        assert_eq!(a.count(&"apple"), (-1).into());
+        assert!(!a.contains(&"apple"));
+        assert!(a.read().is_empty());
+
+        a.apply(a.inc_many("apple", "A", 2));
+        assert!(a.contains(&"apple"));
+        assert_eq!(a.read(), BTreeMap::from([(&"apple", 1.into())]));
+    }
+}
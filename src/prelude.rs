@@ -0,0 +1,19 @@
+//! The types and traits most code using this crate needs, in one import.
+//!
+//! ```rust
+//! use crdts::prelude::*;
+//!
+//! let mut map: Map<&str, MVReg<u8, char>, char> = Map::new();
+//! let ctx: AddCtx<char> = map.read_ctx().derive_add_ctx('a');
+//! map.apply(map.update("x", ctx, |reg, ctx| reg.write(1, ctx)));
+//!
+//! let read: ReadCtx<Option<MVReg<u8, char>>, char> = map.get(&"x");
+//! let rm_ctx: RmCtx<char> = read.derive_rm_ctx();
+//! map.apply(map.rm("x", rm_ctx));
+//! assert_eq!(map.read_ctx().add_clock, VClock::from(Dot::new('a', 1)));
+//! ```
+
+pub use crate::ctx::{AddCtx, ReadCtx, RmCtx};
+pub use crate::{
+    CausalContext, CmRDT, CvRDT, Dot, GSet, LWWReg, MVReg, Map, Orswot, ResetRemove, VClock,
+};
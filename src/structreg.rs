@@ -0,0 +1,131 @@
+//! # StructReg - A struct whose fields merge independently
+//!
+//! Storing a whole struct in an `MVReg` means that concurrent edits to
+//! different fields show up as conflicting values of the entire struct.
+//! `StructReg` instead keeps one `MVReg` per field, in a `Map` keyed by
+//! field, so only concurrent edits to the *same* field conflict.
+//!
+//! Fields are usually named by a small enum (or `&'static str`), and each
+//! field holds values of the same type `V`. Structs with differently typed
+//! fields can use an enum for `V`, or nest a `Map` per field directly.
+//!
+//! # Examples
+//!
+//! ```
+//! use crdts::{CmRDT, CvRDT, StructReg};
+//!
+//! #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+//! enum Field {
+//!     Name,
+//!     Email,
+//! }
+//!
+//! let mut a: StructReg<Field, String, u8> = StructReg::new();
+//! a.apply(a.set(Field::Name, "bob".to_string(), a.read_ctx().derive_add_ctx(1)));
+//! let mut b = a.clone();
+//!
+//! // concurrent edits to different fields
+//! a.apply(a.set(Field::Name, "robert".to_string(), a.read_ctx().derive_add_ctx(1)));
+//! b.apply(b.set(Field::Email, "bob@example.com".to_string(), b.read_ctx().derive_add_ctx(2)));
+//!
+//! a.merge(b);
+//! assert_eq!(a.get(&Field::Name).val, vec!["robert".to_string()]);
+//! assert_eq!(a.get(&Field::Email).val, vec!["bob@example.com".to_string()]);
+//! ```
+
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ctx::{AddCtx, ReadCtx, RmCtx};
+use crate::map::{self, Map};
+use crate::{CmRDT, CvRDT, MVReg};
+
+/// A `Map` of field to `MVReg`, see the module level docs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StructReg<F: Ord, V: Clone, A: Ord + Hash + Clone> {
+    fields: Map<F, MVReg<V, A>, A>,
+}
+
+/// Operations which can be applied to a `StructReg`.
+pub type Op<F, V, A> = map::Op<F, MVReg<V, A>, A>;
+
+impl<F: Ord, V: Clone, A: Ord + Hash + Clone> Default for StructReg<F, V, A> {
+    fn default() -> Self {
+        Self {
+            fields: Map::default(),
+        }
+    }
+}
+
+impl<F: Ord + Clone, V: Clone + Debug, A: Ord + Hash + Clone + Debug> CmRDT for StructReg<F, V, A> {
+    type Op = Op<F, V, A>;
+    type Validation = <Map<F, MVReg<V, A>, A> as CmRDT>::Validation;
+
+    fn validate_op(&self, op: &Self::Op) -> Result<(), Self::Validation> {
+        self.fields.validate_op(op)
+    }
+
+    fn apply(&mut self, op: Self::Op) {
+        self.fields.apply(op)
+    }
+}
+
+impl<F: Ord + Clone + Debug, V: Clone + Debug, A: Ord + Hash + Clone + Debug> CvRDT
+    for StructReg<F, V, A>
+{
+    type Validation = <Map<F, MVReg<V, A>, A> as CvRDT>::Validation;
+
+    fn validate_merge(&self, other: &Self) -> Result<(), Self::Validation> {
+        self.fields.validate_merge(&other.fields)
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.fields.merge(other.fields)
+    }
+}
+
+impl<F: Ord, V: Clone, A: Ord + Hash + Clone + Debug> StructReg<F, V, A> {
+    /// Constructs a StructReg with no fields set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Generate an Op to set the value of a field.
+    pub fn set(&self, field: impl Into<F>, val: V, ctx: AddCtx<A>) -> Op<F, V, A> {
+        self.fields
+            .update(field, ctx, |reg, ctx| reg.write(val, ctx))
+    }
+
+    /// Read the values of a field, more than one value means the field
+    /// was set concurrently. An unset field has no values.
+    pub fn get(&self, field: &F) -> ReadCtx<Vec<V>, A> {
+        let ReadCtx {
+            add_clock,
+            rm_clock,
+            val,
+        } = self.fields.get(field);
+        ReadCtx {
+            add_clock,
+            rm_clock,
+            val: val.map(|reg| reg.read().val).unwrap_or_default(),
+        }
+    }
+
+    /// Generate an Op to unset a field.
+    pub fn unset(&self, field: impl Into<F>, ctx: RmCtx<A>) -> Op<F, V, A> {
+        self.fields.rm(field, ctx)
+    }
+
+    /// Retrieve the current read context
+    pub fn read_ctx(&self) -> ReadCtx<(), A> {
+        self.fields.read_ctx()
+    }
+
+    /// Borrow the underlying `Map` of field registers.
+    pub fn as_map(&self) -> &Map<F, MVReg<V, A>, A> {
+        &self.fields
+    }
+}
@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::error::Error;
 use std::hash::Hash;
 
@@ -24,6 +25,20 @@ pub trait CvRDT {
 
     /// Merge the given CRDT into the current CRDT.
     fn merge(&mut self, other: Self);
+
+    /// Validates the merge with `validate_merge` and only merges if it is
+    /// safe to do so, otherwise the current CRDT is left untouched.
+    ///
+    /// Prefer this over `merge` when the other CRDT comes from a peer you
+    /// don't fully trust.
+    fn try_merge(&mut self, other: Self) -> Result<(), Self::Validation>
+    where
+        Self: Sized,
+    {
+        self.validate_merge(&other)?;
+        self.merge(other);
+        Ok(())
+    }
 }
 
 /// Operation based CRDT's replicate by transmitting each operation.
@@ -72,3 +87,72 @@ pub trait ResetRemove<A: Ord> {
     /// Remove data that is strictly smaller than this clock
     fn reset_remove(&mut self, clock: &VClock<A>);
 }
+
+/// CRDT's that can summarize the causal history of their state in a clock.
+///
+/// This is the counterpart of `ResetRemove`: resetting a CRDT with its own
+/// causal context forgets all of its state.
+pub trait CausalContext<A: Ord> {
+    /// Returns a clock covering every dot in this CRDT's state.
+    fn causal_context(&self) -> VClock<A>;
+
+    /// Compare two replicas of this CRDT by their causal contexts.
+    ///
+    /// Returns `None` when the replicas have diverged, i.e. each has seen
+    /// events the other hasn't. This is not the structural `PartialEq`:
+    /// replicas with equal contexts compare as `Equal` even if one of them
+    /// has applied a remove the other hasn't seen yet.
+    fn causal_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.causal_context().partial_cmp(&other.causal_context())
+    }
+}
+
+/// CRDT's that may hold several concurrent versions of their value at once.
+pub trait Concurrency {
+    /// Returns how many concurrent versions this CRDT currently holds.
+    ///
+    /// Anything above 1 is a conflict the application may want to resolve.
+    fn concurrency(&self) -> usize {
+        1
+    }
+}
+
+/// CRDT's that can tell whether two replicas show the same content.
+///
+/// Unlike the structural `PartialEq`, this ignores the clocks, deferred
+/// removes and other causal history that converged replicas may still hold
+/// in different shapes. Leaf values without such history, e.g. counters,
+/// are compared with their `PartialEq`.
+pub trait LogicalEq {
+    /// Returns true if both replicas hold the same live content.
+    fn logically_eq(&self, other: &Self) -> bool;
+}
+
+/// Types that can estimate how much heap memory they hold.
+///
+/// The estimate counts the entries of the collections a CRDT is made of but
+/// not the allocator's bookkeeping. Keys, members, actors and register values
+/// are counted by their inline size only.
+pub trait HeapSize {
+    /// Returns the estimated number of bytes held on the heap, not counting
+    /// `size_of::<Self>()` itself.
+    fn heap_size(&self) -> usize;
+}
+
+/// CRDT's that pair their values with dots, e.g. `Orswot`, `MVReg` and `Map`.
+///
+/// This ties together the state based merge, `ResetRemove` and `CausalContext`
+/// under one bound so code that ships deltas or garbage collects state can be
+/// written once for all of them.
+pub trait DotStore<A: Ord>: CvRDT + ResetRemove<A> + CausalContext<A> + Sized {
+    /// Join the given dot store into this one, same as `CvRDT::merge`.
+    fn join(&mut self, other: Self) {
+        self.merge(other)
+    }
+
+    /// Forget all values whose dots are covered by this clock, same as
+    /// `ResetRemove::reset_remove`.
+    fn truncate(&mut self, clock: &VClock<A>) {
+        self.reset_remove(clock)
+    }
+}
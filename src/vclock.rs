@@ -16,11 +16,11 @@ use core::cmp::{self, Ordering};
 use core::convert::Infallible;
 use core::fmt::{self, Debug, Display};
 use core::mem;
-use std::collections::{btree_map, BTreeMap};
+use std::collections::{btree_map, BTreeMap, BTreeSet};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::{CmRDT, CvRDT, Dot, DotRange, ResetRemove};
+use crate::{CausalContext, CmRDT, CvRDT, Dot, DotRange, HeapSize, ResetRemove};
 
 /// A `VClock` is a standard vector clock.
 /// It contains a set of "actors" and associated counters.
@@ -35,9 +35,24 @@ use crate::{CmRDT, CvRDT, Dot, DotRange, ResetRemove};
 #[serde(transparent)]
 pub struct VClock<A: Ord> {
     /// dots is the mapping from actors to their associated counters
+    #[serde(
+        deserialize_with = "deserialize_dots",
+        bound(deserialize = "A: Deserialize<'de>")
+    )]
     pub dots: BTreeMap<A, u64>,
 }
 
+/// Deserialized clocks drop zero counters, see `VClock::canonicalize`.
+fn deserialize_dots<'de, A, D>(deserializer: D) -> Result<BTreeMap<A, u64>, D::Error>
+where
+    A: Ord + Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    let mut dots = BTreeMap::deserialize(deserializer)?;
+    dots.retain(|_, counter| *counter > 0);
+    Ok(dots)
+}
+
 impl<A: Ord> Default for VClock<A> {
     fn default() -> Self {
         Self {
@@ -46,6 +61,12 @@ impl<A: Ord> Default for VClock<A> {
     }
 }
 
+/// Clocks are ordered causally: `a < b` iff every event `a` has seen was also
+/// seen by `b`, and concurrent clocks are incomparable.
+///
+/// `VClock` deliberately does not implement `Ord`, a total order would have to
+/// disagree with this one on concurrent clocks. Use `VClock::syntactic_cmp`
+/// where a deterministic total order is needed, e.g. to sort clocks.
 impl<A: Ord> PartialOrd for VClock<A> {
     fn partial_cmp(&self, other: &VClock<A>) -> Option<Ordering> {
         // This algorithm is pretty naive, I think there's a way to
@@ -54,6 +75,8 @@ impl<A: Ord> PartialOrd for VClock<A> {
         // ie. it's None if the ordering changes from Less to Greator
         //     or vice-versa.
 
+        // Structurally identical clocks are the common case when replicas
+        // are in sync, answer those without the dominance scans.
         if self == other {
             Some(Ordering::Equal)
         } else if other.dots.iter().all(|(w, c)| self.get(w) >= *c) {
@@ -79,10 +102,31 @@ impl<A: Ord + Display> Display for VClock<A> {
     }
 }
 
+/// Returned by `VClock::checked_subtract` when the subtracted clock has
+/// seen events this clock has not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnderflowReport<A: Ord> {
+    /// The actors whose counters would have gone negative, along with the
+    /// counter the subtracted clock had for them.
+    pub ahead: VClock<A>,
+}
+
+impl<A: Ord + Debug> Display for UnderflowReport<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "subtracted clock is ahead for {:?}", self.ahead.dots)
+    }
+}
+
+impl<A: Ord + Debug> std::error::Error for UnderflowReport<A> {}
+
 impl<A: Ord> ResetRemove<A> for VClock<A> {
     /// Forget any actors that have smaller counts than the
     /// count in the given vclock
     fn reset_remove(&mut self, other: &Self) {
+        if other.is_empty() {
+            return;
+        }
+
         for Dot { actor, counter } in other.iter() {
             if counter >= self.get(actor) {
                 self.dots.remove(actor);
@@ -91,6 +135,18 @@ impl<A: Ord> ResetRemove<A> for VClock<A> {
     }
 }
 
+impl<A: Ord> HeapSize for VClock<A> {
+    fn heap_size(&self) -> usize {
+        self.dots.len() * mem::size_of::<(A, u64)>()
+    }
+}
+
+impl<A: Ord + Clone> CausalContext<A> for VClock<A> {
+    fn causal_context(&self) -> VClock<A> {
+        self.clone()
+    }
+}
+
 impl<A: Ord + Clone + Debug> CmRDT for VClock<A> {
     type Op = Dot<A>;
     type Validation = DotRange<A>;
@@ -123,9 +179,7 @@ impl<A: Ord + Clone + Debug> CmRDT for VClock<A> {
     /// assert_eq!(v.get(&"A"), 2);
     /// ```
     fn apply(&mut self, dot: Self::Op) {
-        if self.get(&dot.actor) < dot.counter {
-            self.dots.insert(dot.actor, dot.counter);
-        }
+        self.apply_dot(dot);
     }
 }
 
@@ -149,8 +203,45 @@ impl<A: Ord> VClock<A> {
         Default::default()
     }
 
+    /// Drop actors with a zero counter, which carry no information.
+    ///
+    /// Applying dots never adds a zero counter and deserializing drops
+    /// them, so clocks only get zero counters when `dots` is filled in
+    /// directly, and such a clock would not compare equal to the same clock
+    /// without them.
+    ///
+    /// ``` rust
+    /// use crdts::{Dot, VClock};
+    /// let mut clock = VClock::from(Dot::new("A", 1));
+    /// clock.dots.insert("B", 0);
+    /// assert_ne!(clock, VClock::from(Dot::new("A", 1)));
+    ///
+    /// clock.canonicalize();
+    /// assert_eq!(clock, VClock::from(Dot::new("A", 1)));
+    /// ```
+    pub fn canonicalize(&mut self) {
+        self.dots.retain(|_, counter| *counter > 0);
+    }
+
     /// Returns a clone of self but with information that is older than given clock is
     /// forgotten
+    ///
+    /// This is the delta of `self` relative to `base_clock`: only the actors
+    /// `self` is ahead on are kept, so a peer holding `base_clock` can
+    /// rebuild their join by merging the (usually much smaller) delta in.
+    ///
+    /// ``` rust
+    /// use crdts::{CvRDT, VClock};
+    /// let base = VClock::from_pairs(vec![("A", 4), ("B", 7), ("C", 2)]);
+    /// let clock = VClock::from_pairs(vec![("A", 4), ("B", 9), ("C", 1)]);
+    ///
+    /// let delta = clock.clone_without(&base);
+    /// assert_eq!(delta, VClock::from_pairs(vec![("B", 9)]));
+    ///
+    /// let mut rebuilt = base.clone();
+    /// rebuilt.merge(delta);
+    /// assert_eq!(rebuilt, VClock::from_pairs(vec![("A", 4), ("B", 9), ("C", 2)]));
+    /// ```
     pub fn clone_without(&self, base_clock: &VClock<A>) -> VClock<A>
     where
         A: Clone,
@@ -160,6 +251,30 @@ impl<A: Ord> VClock<A> {
         cloned
     }
 
+    /// Like `clone_without` but fails if `other` is ahead of this clock for
+    /// any actor, reporting the offending actors.
+    ///
+    /// # Examples
+    /// ```
+    /// use crdts::{VClock, Dot};
+    /// let a: VClock<_> = vec![Dot::new("A", 2), Dot::new("B", 1)].into_iter().collect();
+    /// let b: VClock<_> = vec![Dot::new("A", 3)].into_iter().collect();
+    ///
+    /// assert_eq!(a.checked_subtract(&Dot::new("A", 2).into()), Ok(Dot::new("B", 1).into()));
+    /// assert_eq!(a.checked_subtract(&b).unwrap_err().ahead, b);
+    /// ```
+    pub fn checked_subtract(&self, other: &VClock<A>) -> Result<VClock<A>, UnderflowReport<A>>
+    where
+        A: Clone,
+    {
+        let ahead = other.clone_without(self);
+        if ahead.is_empty() {
+            Ok(self.clone_without(other))
+        } else {
+            Err(UnderflowReport { ahead })
+        }
+    }
+
     /// Generate Op to increment an actor's counter.
     ///
     /// # Examples
@@ -196,11 +311,57 @@ impl<A: Ord> VClock<A> {
     }
 
     /// Return the Dot for a given actor
+    ///
+    /// This is the actor's latest dot seen by this clock, with a counter of 0
+    /// if the actor is unseen. Use `inc` for the dot of the actor's next event.
     pub fn dot(&self, actor: A) -> Dot<A> {
         let counter = self.get(&actor);
         Dot::new(actor, counter)
     }
 
+    /// Split this clock into the given actor's dot, if it has been seen, and
+    /// a clock of every other actor.
+    ///
+    /// ``` rust
+    /// use crdts::{Dot, VClock};
+    /// let clock = VClock::from_pairs([("A", 2), ("B", 5)]);
+    ///
+    /// assert_eq!(clock.split_at(&"A"), (Some(Dot::new("A", 2)), VClock::from_pairs([("B", 5)])));
+    /// assert_eq!(clock.split_at(&"C"), (None, clock.clone()));
+    /// ```
+    pub fn split_at(&self, actor: &A) -> (Option<Dot<A>>, VClock<A>)
+    where
+        A: Clone,
+    {
+        let mut rest = self.clone();
+        let dot = rest
+            .dots
+            .remove_entry(actor)
+            .map(|(actor, counter)| Dot::new(actor, counter));
+        (dot, rest)
+    }
+
+    /// Monotonically adds the given dot to this VClock, returning `true` if
+    /// the actor's counter advanced and `false` if the dot was already seen.
+    ///
+    /// # Examples
+    /// ```
+    /// use crdts::{VClock, Dot};
+    /// let mut v = VClock::new();
+    ///
+    /// assert!(v.apply_dot(Dot::new("A", 2)));
+    /// assert!(!v.apply_dot(Dot::new("A", 2)));
+    /// assert!(!v.apply_dot(Dot::new("A", 1)));
+    /// ```
+    pub fn apply_dot(&mut self, dot: Dot<A>) -> bool {
+        if self.get(&dot.actor) < dot.counter {
+            self.dots.insert(dot.actor, dot.counter);
+            true
+        } else {
+            false
+        }
+    }
+
     /// True if two vector clocks have diverged.
     ///
     /// # Examples
@@ -215,6 +376,49 @@ impl<A: Ord> VClock<A> {
         self.partial_cmp(other).is_none()
     }
 
+    /// Splits the difference between two clocks by side: the first clock
+    /// holds the actors this clock is ahead on, the second those `other` is
+    /// ahead on, each with the counter of the side that is ahead.
+    ///
+    /// Both halves are non-empty exactly when the clocks are concurrent.
+    ///
+    /// ``` rust
+    /// use crdts::VClock;
+    /// let a = VClock::from_pairs(vec![("A", 3), ("B", 1), ("C", 2)]);
+    /// let b = VClock::from_pairs(vec![("A", 1), ("B", 4), ("C", 2)]);
+    ///
+    /// let (a_ahead, b_ahead) = a.divergence(&b);
+    /// assert_eq!(a_ahead, VClock::from_pairs(vec![("A", 3)]));
+    /// assert_eq!(b_ahead, VClock::from_pairs(vec![("B", 4)]));
+    /// ```
+    pub fn divergence(&self, other: &Self) -> (VClock<A>, VClock<A>)
+    where
+        A: Clone,
+    {
+        (self.clone_without(other), other.clone_without(self))
+    }
+
+    /// A total order over clocks that compares their (actor, counter) pairs
+    /// lexicographically, in actor order.
+    ///
+    /// This says nothing about causality: concurrent clocks get an arbitrary
+    /// but deterministic order, and a clock can sort before one it dominates.
+    /// Use it to sort or dedup clocks, and `partial_cmp` to reason about
+    /// happened-before.
+    ///
+    /// ``` rust
+    /// use std::cmp::Ordering;
+    /// use crdts::VClock;
+    /// let a = VClock::from_pairs(vec![("A", 1), ("B", 2)]);
+    /// let b = VClock::from_pairs(vec![("A", 2)]);
+    ///
+    /// assert_eq!(a.partial_cmp(&b), None);
+    /// assert_eq!(a.syntactic_cmp(&b), Ordering::Less);
+    /// ```
+    pub fn syntactic_cmp(&self, other: &Self) -> Ordering {
+        self.dots.cmp(&other.dots)
+    }
+
     /// Returns `true` if this vector clock contains nothing.
     pub fn is_empty(&self) -> bool {
         self.dots.is_empty()
@@ -222,6 +426,23 @@ impl<A: Ord> VClock<A> {
 
     /// Returns the common elements (same actor and counter)
     /// for two `VClock` instances.
+    ///
+    /// Actors missing from either clock, or whose counters differ, are
+    /// dropped. `Map` and `Orswot` rely on this exact-match behaviour in
+    /// their merges to find the dots both replicas have seen for an entry.
+    /// If you want the per-actor minimum instead, use `glb`.
+    ///
+    /// ``` rust
+    /// use crdts::{Dot, VClock};
+    /// let a = VClock::from_pairs(vec![("A", 2), ("B", 1), ("C", 4)]);
+    /// let b = VClock::from_pairs(vec![("A", 2), ("B", 3)]);
+    ///
+    /// assert_eq!(VClock::intersection(&a, &b), VClock::from(Dot::new("A", 2)));
+    ///
+    /// let mut min = a.clone();
+    /// min.glb(&b);
+    /// assert_eq!(min, VClock::from_pairs(vec![("A", 2), ("B", 1)]));
+    /// ```
     pub fn intersection(left: &VClock<A>, right: &VClock<A>) -> VClock<A>
     where
         A: Clone,
@@ -268,6 +489,66 @@ impl<A: Ord> VClock<A> {
             .collect();
     }
 
+    /// Caps the counter of every actor that is also in `frontier` to the
+    /// frontier's counter. Unlike `glb`, actors missing from `frontier` are
+    /// kept as is rather than dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// use crdts::VClock;
+    /// let mut clock = VClock::from_pairs(vec![("A", 5), ("B", 1), ("C", 3)]);
+    /// clock.clamp(&VClock::from_pairs(vec![("A", 2), ("B", 4)]));
+    /// assert_eq!(clock, VClock::from_pairs(vec![("A", 2), ("B", 1), ("C", 3)]));
+    /// ```
+    pub fn clamp(&mut self, frontier: &VClock<A>) {
+        self.dots.retain(|actor, counter| {
+            if let Some(bound) = frontier.dots.get(actor) {
+                *counter = cmp::min(*counter, *bound);
+            }
+            // a counter of 0 is implied for missing actors, don't store it
+            *counter > 0
+        });
+    }
+
+    /// Build a vclock from `(actor, counter)` pairs, if an actor appears
+    /// more than once the largest counter is kept.
+    ///
+    /// # Examples
+    /// ```
+    /// use crdts::VClock;
+    /// let clock = VClock::from_pairs(vec![("A", 2), ("B", 1), ("A", 3)]);
+    /// assert_eq!(clock.get(&"A"), 3);
+    /// assert_eq!(clock.into_pairs().collect::<Vec<_>>(), vec![("A", 3), ("B", 1)]);
+    /// ```
+    pub fn from_pairs(pairs: impl IntoIterator<Item = (A, u64)>) -> Self {
+        let mut clock = VClock::new();
+        for pair in pairs {
+            clock.apply_dot(pair.into());
+        }
+        clock
+    }
+
+    /// Build a vclock from dots, keeping the highest counter seen for each
+    /// actor, same as collecting the dots into a `VClock`.
+    ///
+    /// ``` rust
+    /// use crdts::{Dot, VClock};
+    /// let clock = VClock::from_dots([Dot::new("A", 3), Dot::new("B", 1), Dot::new("A", 2)]);
+    /// assert_eq!(clock, VClock::from_pairs([("A", 3), ("B", 1)]));
+    /// ```
+    pub fn from_dots(dots: impl IntoIterator<Item = Dot<A>>) -> Self {
+        let mut clock = VClock::new();
+        for dot in dots {
+            clock.apply_dot(dot);
+        }
+        clock
+    }
+
+    /// Consumes the vclock and returns its `(actor, counter)` pairs ordered by actor.
+    pub fn into_pairs(self) -> impl Iterator<Item = (A, u64)> {
+        self.dots.into_iter()
+    }
+
     /// Returns an iterator over the dots in this vclock
     pub fn iter(&self) -> impl Iterator<Item = Dot<&A>> {
         self.dots.iter().map(|(a, c)| Dot {
@@ -277,6 +558,167 @@ impl<A: Ord> VClock<A> {
     }
 }
 
+/// Tracks which events every known actor has acknowledged.
+///
+/// Each actor periodically reports the clock of the ops it has applied. An
+/// event is causally stable once every known actor has acknowledged it: no
+/// op concurrent with it can arrive anymore, so side effects tied to it can
+/// run exactly once.
+///
+/// ``` rust
+/// use crdts::{Dot, VClock};
+/// use crdts::vclock::StabilityTracker;
+///
+/// let mut tracker = StabilityTracker::new(vec!["A", "B"]);
+/// tracker.ack("A", VClock::from(Dot::new("A", 1)));
+/// assert!(!tracker.is_stable(&Dot::new("A", 1)));
+///
+/// tracker.ack("B", VClock::from(Dot::new("A", 1)));
+/// assert!(tracker.is_stable(&Dot::new("A", 1)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StabilityTracker<A: Ord> {
+    acks: BTreeMap<A, VClock<A>>,
+}
+
+impl<A: Ord + Clone> StabilityTracker<A> {
+    /// Constructs a tracker for the given actors, none of which have
+    /// acknowledged anything yet.
+    pub fn new(actors: impl IntoIterator<Item = A>) -> Self {
+        Self {
+            acks: actors.into_iter().map(|a| (a, VClock::new())).collect(),
+        }
+    }
+
+    /// Records that `actor` has seen every event in `clock`.
+    ///
+    /// Acks only ever grow, a stale ack doesn't undo a later one. Acks from
+    /// actors we didn't know about add them to the known actors.
+    pub fn ack(&mut self, actor: A, clock: VClock<A>) {
+        let ack = self.acks.entry(actor).or_default();
+        for dot in clock {
+            ack.apply_dot(dot);
+        }
+    }
+
+    /// Returns the clock of events every known actor has acknowledged.
+    pub fn stable_clock(&self) -> VClock<A> {
+        let mut acks = self.acks.values();
+        let mut stable = acks.next().cloned().unwrap_or_default();
+        for ack in acks {
+            stable.glb(ack);
+        }
+        stable
+    }
+
+    /// Returns true if every known actor has acknowledged this event.
+    pub fn is_stable(&self, dot: &Dot<A>) -> bool {
+        !self.acks.is_empty()
+            && self
+                .acks
+                .values()
+                .all(|ack| ack.get(&dot.actor) >= dot.counter)
+    }
+}
+
+/// Tracks the dots applied per actor, including ones that arrived ahead of
+/// a missing predecessor.
+///
+/// A `VClock` only holds the highest counter per actor, so once a later dot
+/// advances it, the dots that were skipped over can no longer be seen. This
+/// tracker keeps the contiguous prefix as a clock and the out of order dots
+/// on the side, so the missing ones can be asked for again.
+///
+/// ``` rust
+/// use crdts::{Dot, VClock};
+/// use crdts::vclock::GappedClock;
+///
+/// let mut seen = GappedClock::new();
+/// for counter in [1, 2, 4] {
+///     seen.apply(Dot::new("A", counter));
+/// }
+/// assert_eq!(seen.clock(), &VClock::from(Dot::new("A", 2)));
+/// assert_eq!(seen.missing_dots(), vec![Dot::new("A", 3)]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GappedClock<A: Ord> {
+    clock: VClock<A>,
+    ahead: BTreeMap<A, BTreeSet<u64>>,
+}
+
+impl<A: Ord> Default for GappedClock<A> {
+    fn default() -> Self {
+        Self {
+            clock: VClock::new(),
+            ahead: BTreeMap::new(),
+        }
+    }
+}
+
+impl<A: Ord + Clone> GappedClock<A> {
+    /// Constructs a tracker that has seen no dots.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that the op with this dot was applied.
+    ///
+    /// Dots filling a gap fold the out of order dots that follow them into
+    /// the contiguous clock.
+    pub fn apply(&mut self, dot: Dot<A>) {
+        let next = self.clock.get(&dot.actor) + 1;
+        if dot.counter < next {
+            return;
+        }
+        if dot.counter > next {
+            self.ahead.entry(dot.actor).or_default().insert(dot.counter);
+            return;
+        }
+
+        let mut counter = dot.counter;
+        if let Some(ahead) = self.ahead.get_mut(&dot.actor) {
+            while ahead.remove(&(counter + 1)) {
+                counter += 1;
+            }
+            if ahead.is_empty() {
+                self.ahead.remove(&dot.actor);
+            }
+        }
+        self.clock.apply_dot(Dot::new(dot.actor, counter));
+    }
+
+    /// Returns true if the op with this dot was applied.
+    pub fn contains(&self, dot: &Dot<A>) -> bool {
+        self.clock.get(&dot.actor) >= dot.counter
+            || self
+                .ahead
+                .get(&dot.actor)
+                .is_some_and(|ahead| ahead.contains(&dot.counter))
+    }
+
+    /// Returns the clock of the dots applied without gaps.
+    pub fn clock(&self) -> &VClock<A> {
+        &self.clock
+    }
+
+    /// Returns the dots that were skipped by a later dot of the same actor,
+    /// ordered by actor and counter.
+    ///
+    /// Dots past the highest one seen for an actor aren't known to exist and
+    /// are not reported.
+    pub fn missing_dots(&self) -> Vec<Dot<A>> {
+        let mut missing = Vec::new();
+        for (actor, ahead) in self.ahead.iter() {
+            let mut expected = self.clock.get(actor) + 1;
+            for &counter in ahead {
+                missing.extend((expected..counter).map(|c| Dot::new(actor.clone(), c)));
+                expected = counter + 1;
+            }
+        }
+        missing
+    }
+}
+
 /// Generated from calls to VClock::into_iter()
 pub struct IntoIter<A: Ord> {
     btree_iter: btree_map::IntoIter<A, u64>,
@@ -304,6 +746,8 @@ impl<A: Ord> std::iter::IntoIterator for VClock<A> {
     }
 }
 
+/// Collecting dots keeps the highest counter seen for each actor, see
+/// `VClock::from_dots`.
 impl<A: Ord + Clone + Debug> std::iter::FromIterator<Dot<A>> for VClock<A> {
     fn from_iter<I: IntoIterator<Item = Dot<A>>>(iter: I) -> Self {
         let mut clock = VClock::default();
@@ -316,6 +760,7 @@ impl<A: Ord + Clone + Debug> std::iter::FromIterator<Dot<A>> for VClock<A> {
     }
 }
 
+/// The clock that has seen exactly the events up to this dot.
 impl<A: Ord + Clone + Debug> From<Dot<A>> for VClock<A> {
     fn from(dot: Dot<A>) -> Self {
         let mut clock = VClock::default();
@@ -0,0 +1,86 @@
+use crdts::{CausalContext, CmRDT, DotStore, MVReg, Map, Orswot};
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
+/// Checks the join laws and that truncating with a store's own causal
+/// context forgets all of its state.
+fn assert_dot_store_laws<S>(a: S, b: S, c: S)
+where
+    S: DotStore<u8> + Default + Clone + PartialEq + Debug,
+{
+    let mut ab = a.clone();
+    ab.join(b.clone());
+    let mut ba = b.clone();
+    ba.join(a.clone());
+    assert_eq!(ab, ba);
+
+    let mut ab_c = ab.clone();
+    ab_c.join(c.clone());
+    let mut bc = b;
+    bc.join(c);
+    let mut a_bc = a.clone();
+    a_bc.join(bc);
+    assert_eq!(ab_c, a_bc);
+
+    let mut aa = a.clone();
+    aa.join(a.clone());
+    assert_eq!(aa, a);
+
+    let mut truncated = ab_c.clone();
+    truncated.truncate(&ab_c.causal_context());
+    assert_eq!(truncated, S::default());
+}
+
+/// Checks that `advanced`, which was built on top of `base`, is causally
+/// ahead of it and that `diverged` is concurrent with `advanced`.
+fn assert_causal_order<S: CausalContext<u8>>(base: &S, advanced: &S, diverged: &S) {
+    assert_eq!(base.causal_cmp(base), Some(Ordering::Equal));
+    assert_eq!(base.causal_cmp(advanced), Some(Ordering::Less));
+    assert_eq!(advanced.causal_cmp(base), Some(Ordering::Greater));
+    assert_eq!(advanced.causal_cmp(diverged), None);
+}
+
+#[test]
+fn test_orswot_is_a_dot_store() {
+    let mut a = Orswot::new();
+    a.apply(a.add(1, a.read_ctx().derive_add_ctx(1)));
+    let mut b = a.clone();
+    let mut c = a.clone();
+    let base = a.clone();
+    a.apply(a.add(2, a.read_ctx().derive_add_ctx(1)));
+    b.apply(b.rm(1, b.contains(&1).derive_rm_ctx()));
+    c.apply(c.add(3, c.read_ctx().derive_add_ctx(3)));
+
+    assert_causal_order(&base, &a, &c);
+    assert_dot_store_laws(a, b, c);
+}
+
+#[test]
+fn test_mvreg_is_a_dot_store() {
+    let mut a = MVReg::new();
+    a.apply(a.write(1, a.read_ctx().derive_add_ctx(1)));
+    let mut b = a.clone();
+    let mut c = a.clone();
+    let base = a.clone();
+    a.apply(a.write(2, a.read_ctx().derive_add_ctx(1)));
+    b.apply(b.write(3, b.read_ctx().derive_add_ctx(2)));
+    c.apply(c.write(4, c.read_ctx().derive_add_ctx(3)));
+
+    assert_causal_order(&base, &a, &c);
+    assert_dot_store_laws(a, b, c);
+}
+
+#[test]
+fn test_map_is_a_dot_store() {
+    let mut a: Map<u8, MVReg<u8, u8>, u8> = Map::new();
+    a.apply(a.update(1, a.read_ctx().derive_add_ctx(1), |r, ctx| r.write(1, ctx)));
+    let mut b = a.clone();
+    let mut c = a.clone();
+    let base = a.clone();
+    a.apply(a.update(2, a.read_ctx().derive_add_ctx(1), |r, ctx| r.write(2, ctx)));
+    b.apply(b.rm(1, b.get(&1).derive_rm_ctx()));
+    c.apply(c.update(1, c.read_ctx().derive_add_ctx(3), |r, ctx| r.write(3, ctx)));
+
+    assert_causal_order(&base, &a, &c);
+    assert_dot_store_laws(a, b, c);
+}
@@ -1,7 +1,7 @@
 use num::BigRational;
 
 use crdts::glist::GList;
-use crdts::{CmRDT, Identifier};
+use crdts::{CmRDT, CvRDT, Identifier};
 
 #[test]
 fn test_concurrent_inserts_with_same_identifier_can_be_split() {
@@ -41,6 +41,54 @@ fn test_append_increments_entry() {
     assert_eq!("abc", glist.read::<String>());
 }
 
+#[test]
+fn test_concurrent_appends_converge() {
+    let mut log_a = GList::new();
+    log_a.apply(log_a.append("start", log_a.read_ctx().derive_add_ctx('A')));
+    let mut log_b = log_a.clone();
+
+    log_a.apply(log_a.append("a1", log_a.read_ctx().derive_add_ctx('A')));
+    log_a.apply(log_a.append("a2", log_a.read_ctx().derive_add_ctx('A')));
+    log_b.apply(log_b.append("b1", log_b.read_ctx().derive_add_ctx('B')));
+
+    let mut merged_ab = log_a.clone();
+    merged_ab.merge(log_b.clone());
+    let mut merged_ba = log_b;
+    merged_ba.merge(log_a);
+
+    assert_eq!(merged_ab, merged_ba);
+    assert_eq!(merged_ab.len(), 4);
+    assert_eq!(merged_ab.first().map(|id| id.value().1), Some("start"));
+
+    // appends after the merge land at the end of the agreed upon order
+    merged_ab.apply(merged_ab.append("end", merged_ab.read_ctx().derive_add_ctx('B')));
+    assert_eq!(merged_ab.last().map(|id| id.value().1), Some("end"));
+}
+
+#[test]
+fn test_concurrent_equal_appends_are_kept() {
+    let mut log_a = GList::new();
+    let mut log_b = log_a.clone();
+    let mut log_c = log_a.clone();
+    log_a.apply(log_a.append("tick", log_a.read_ctx().derive_add_ctx('A')));
+    log_b.apply(log_b.append("tick", log_b.read_ctx().derive_add_ctx('B')));
+    log_c.apply(log_c.append("tick", log_c.read_ctx().derive_add_ctx('C')));
+
+    let mut merged_abc = log_a.clone();
+    merged_abc.merge(log_b.clone());
+    merged_abc.merge(log_c.clone());
+    let mut merged_cba = log_c;
+    merged_cba.merge(log_b);
+    merged_cba.merge(log_a);
+
+    assert_eq!(merged_abc, merged_cba);
+    assert_eq!(merged_abc.len(), 3);
+    // concurrent appends are ordered by their dots
+    let actors: Vec<char> = merged_abc.iter().map(|id| id.value().0.actor).collect();
+    assert_eq!(actors, vec!['A', 'B', 'C']);
+    assert_eq!(merged_abc.read_ctx().add_clock.get(&'B'), 1);
+}
+
 #[test]
 fn test_insert_at_front() {
     let mut glist: GList<u8> = Default::default();
@@ -58,7 +106,7 @@ fn test_insert_at_front() {
 #[cfg(feature = "quickcheck")]
 mod prop_tests {
     use super::*;
-    use crdts::{glist::Op, CvRDT};
+    use crdts::glist::Op;
     use quickcheck_macros::quickcheck;
 
     #[quickcheck]
@@ -0,0 +1,77 @@
+use crdts::json::ToValue;
+use crdts::{CmRDT, LWWMap, MVReg, Map, Orswot};
+use serde_json::json;
+
+#[test]
+fn test_nested_map_view() {
+    let mut m: Map<&str, Map<&str, MVReg<u8, &str>, &str>, &str> = Map::new();
+    let mut other = m.clone();
+    m.apply(
+        m.update("alice", m.read_ctx().derive_add_ctx("A"), |inner, ctx| {
+            inner.update("age", ctx, |reg, ctx| reg.write(30, ctx))
+        }),
+    );
+    m.apply(
+        m.update("bob", m.read_ctx().derive_add_ctx("A"), |inner, ctx| {
+            inner.update("age", ctx, |reg, ctx| reg.write(41, ctx))
+        }),
+    );
+    other.apply(
+        other.update("bob", other.read_ctx().derive_add_ctx("B"), |inner, ctx| {
+            inner.update("age", ctx, |reg, ctx| reg.write(40, ctx))
+        }),
+    );
+    m.apply(m.rm("alice", m.get(&"alice").derive_rm_ctx()));
+    crdts::CvRDT::merge(&mut m, other);
+
+    assert_eq!(m.to_value(), json!({ "bob": { "age": [41, 40] } }));
+}
+
+#[test]
+fn test_set_and_register_views() {
+    let mut set = Orswot::new();
+    set.apply(set.add_all(vec!["b", "c", "a"], set.read_ctx().derive_add_ctx(1)));
+    assert_eq!(set.to_value(), json!(["a", "b", "c"]));
+
+    let mut lww: LWWMap<u8, &str, u64, u8> = LWWMap::new();
+    lww.apply(lww.set(1, "one", 5, lww.read_ctx().derive_add_ctx(1)));
+    assert_eq!(lww.as_map().to_value(), json!({ "1": "one" }));
+}
+
+#[test]
+fn test_op_log_surfaces_truncated_records() {
+    type M = Map<u8, MVReg<u8, u8>, u8>;
+    let mut m: M = Map::new();
+    let mut ops = Vec::new();
+    for key in 0..3 {
+        let op = m.update(key, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(key, ctx)
+        });
+        m.apply(op.clone());
+        ops.push(op);
+    }
+    let mut bytes = Vec::new();
+    M::write_op_log(&mut bytes, &ops).unwrap();
+    assert_eq!(M::from_op_log(bytes.as_slice()).unwrap(), m);
+
+    bytes.truncate(bytes.len() - 1);
+    assert!(matches!(
+        M::from_op_log(bytes.as_slice()),
+        Err(crdts::map::OpLogError::Io(_))
+    ));
+
+    // a corrupt length prefix is reported rather than allocated
+    let mut corrupt = (u64::MAX / 2).to_le_bytes().to_vec();
+    corrupt.extend(b"{}");
+    assert!(matches!(
+        M::from_op_log(corrupt.as_slice()),
+        Err(crdts::map::OpLogError::Io(_))
+    ));
+
+    let mut garbage = 3u64.to_le_bytes().to_vec();
+    garbage.extend(b"{{{");
+    assert!(matches!(
+        M::from_op_log(garbage.as_slice()),
+        Err(crdts::map::OpLogError::Decode(_))
+    ));
+}
@@ -0,0 +1,84 @@
+use crdts::{CmRDT, CvRDT, LWWMap};
+
+type TMap = LWWMap<&'static str, u8, u64, u8>;
+
+#[test]
+fn test_set_get_rm() {
+    let mut m = TMap::new();
+    assert_eq!(m.get(&"a"), None);
+
+    m.apply(m.set("a", 1, 1, m.read_ctx().derive_add_ctx(1)));
+    assert_eq!(m.get(&"a"), Some(&1));
+
+    // a write with an older marker is ignored
+    m.apply(m.set("a", 2, 0, m.read_ctx().derive_add_ctx(1)));
+    assert_eq!(m.get(&"a"), Some(&1));
+
+    m.apply(m.set("a", 3, 5, m.read_ctx().derive_add_ctx(1)));
+    assert_eq!(m.get(&"a"), Some(&3));
+
+    m.apply(m.rm("a", m.read_ctx().derive_rm_ctx()));
+    assert_eq!(m.get(&"a"), None);
+    assert!(m.is_empty().val);
+}
+
+#[test]
+fn test_concurrent_sets_converge_on_latest_marker() {
+    let mut a = TMap::new();
+    let mut b = TMap::new();
+
+    let op_a = a.set("k", 10, 2, a.read_ctx().derive_add_ctx(1));
+    let op_b = b.set("k", 20, 3, b.read_ctx().derive_add_ctx(2));
+    a.apply(op_a.clone());
+    b.apply(op_b.clone());
+
+    let mut merged = a.clone();
+    merged.merge(b.clone());
+    assert_eq!(merged.get(&"k"), Some(&20));
+
+    // op based replication ends in the same state
+    a.apply(op_b);
+    b.apply(op_a);
+    assert_eq!(a, b);
+    assert_eq!(a, merged);
+}
+
+#[test]
+fn test_concurrent_set_survives_remove() {
+    let mut a = TMap::new();
+    a.apply(a.set("k", 1, 1, a.read_ctx().derive_add_ctx(1)));
+    let mut b = a.clone();
+
+    a.apply(a.rm("k", a.read_ctx().derive_rm_ctx()));
+    b.apply(b.set("k", 2, 2, b.read_ctx().derive_add_ctx(2)));
+
+    a.merge(b);
+    assert_eq!(a.get(&"k"), Some(&2));
+}
+
+#[test]
+fn test_remove_racing_lower_marker_write_converges() {
+    let mut r = TMap::new();
+    r.apply(r.set("k", 1, 10, r.read_ctx().derive_add_ctx(1)));
+    let mut s = r.clone();
+    let b = r.clone();
+
+    let rm = r.rm("k", r.read_ctx().derive_rm_ctx());
+    let set = b.set("k", 2, 5, b.read_ctx().derive_add_ctx(2));
+
+    let mut merged = r.clone();
+    r.apply(rm.clone());
+    r.apply(set.clone());
+    s.apply(set.clone());
+    s.apply(rm.clone());
+    assert_eq!(r, s);
+    assert_eq!(r.get(&"k"), Some(&2));
+    assert_eq!(s.get(&"k"), Some(&2));
+
+    // state based replication agrees
+    let mut removed = merged.clone();
+    removed.apply(rm);
+    merged.apply(set);
+    merged.merge(removed);
+    assert_eq!(merged, r);
+}
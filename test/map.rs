@@ -1,4 +1,7 @@
-use crdts::{map, mvreg, CmRDT, CvRDT, Dot, DotRange, MVReg, Map, VClock};
+use std::collections::BTreeMap;
+
+use crdts::ctx::{ReadCtx, RmCtx};
+use crdts::{map, mvreg, CmRDT, CvRDT, Dot, DotRange, LogicalEq, MVReg, Map, VClock};
 
 type TActor = u8;
 type TKey = u8;
@@ -7,103 +10,1570 @@ type TOp = map::Op<TKey, Map<TKey, TVal, TActor>, TActor>;
 type TMap = Map<TKey, Map<TKey, TVal, TActor>, TActor>;
 
 #[test]
-fn test_new() {
-    let m: Map<u8, MVReg<u8, _>, u8> = Map::new();
-    assert_eq!(m.len().val, 0);
-    assert!(m.is_empty().val);
+fn test_new() {
+    let m: Map<u8, MVReg<u8, _>, u8> = Map::new();
+    assert_eq!(m.len().val, 0);
+    assert!(m.is_empty().val);
+}
+
+#[test]
+fn test_is_empty() {
+    let mut m: Map<&str, Map<&str, MVReg<&str, _>, _>, &str> = Map::new();
+    let is_empty_read = m.is_empty();
+    assert!(is_empty_read.val);
+
+    m.apply(
+        m.update("user_32", is_empty_read.derive_add_ctx("A"), |map, ctx| {
+            map.update("name", ctx, |reg, ctx| reg.write("bob", ctx))
+        }),
+    );
+
+    assert!(!m.is_empty().val);
+}
+
+#[test]
+fn test_update() {
+    let mut m: TMap = Map::new();
+
+    // constructs a default value if does not exist
+    let ctx = m.get(&101).derive_add_ctx(1);
+    let op = m.update(101, ctx, |map, ctx| {
+        map.update(110, ctx, |reg, ctx| reg.write(2, ctx))
+    });
+
+    assert_eq!(
+        op,
+        map::Op::Up {
+            dot: Dot::new(1, 1),
+            key: 101,
+            op: map::Op::Up {
+                dot: Dot::new(1, 1),
+                key: 110,
+                op: mvreg::Op::Put {
+                    clock: Dot::new(1, 1).into(),
+                    val: 2
+                }
+            }
+        }
+    );
+
+    // update should not mutate the map
+    assert_eq!(m, Map::new());
+
+    m.apply(op);
+
+    assert_eq!(
+        m.get(&101)
+            .val
+            .and_then(|m2| m2.get(&110).val)
+            .map(|r| r.read().val),
+        Some(vec![2])
+    );
+
+    // the map should give the latest val to the closure
+    m.apply(m.update(101, m.get(&101).derive_add_ctx(1), |map, ctx| {
+        map.update(110, ctx, |reg, ctx| {
+            assert_eq!(reg.read().val, vec![2]);
+            reg.write(6, ctx)
+        })
+    }));
+
+    assert_eq!(
+        m.get(&101)
+            .val
+            .and_then(|m2| m2.get(&110).val)
+            .map(|r| r.read().val),
+        Some(vec![6])
+    );
+}
+
+#[test]
+fn test_try_update() {
+    let mut m: TMap = Map::new();
+
+    // a failing updater produces no op
+    let ctx = m.get(&101).derive_add_ctx(1);
+    let res: Result<TOp, &str> = m.try_update(101, ctx, |_, _| Err("invalid"));
+    assert_eq!(res, Err("invalid"));
+
+    // a succeeding updater produces the same op as `update`
+    let ctx = m.get(&101).derive_add_ctx(1);
+    let op = m.update(101, ctx, |map, ctx| {
+        map.update(110, ctx, |reg, ctx| reg.write(2, ctx))
+    });
+    let ctx = m.get(&101).derive_add_ctx(1);
+    let res: Result<TOp, &str> = m.try_update(101, ctx, |map, ctx| {
+        Ok(map.update(110, ctx, |reg, ctx| reg.write(2, ctx)))
+    });
+    assert_eq!(res, Ok(op.clone()));
+
+    m.apply(op);
+    assert_eq!(
+        m.get(&101)
+            .val
+            .and_then(|m2| m2.get(&110).val)
+            .map(|r| r.read().val),
+        Some(vec![2])
+    );
+}
+
+#[test]
+fn test_remove() {
+    let mut m: TMap = Map::new();
+
+    let add_ctx = m.len().derive_add_ctx(1);
+    let mut inner_map: Map<TKey, TVal, TActor> = Map::new();
+    inner_map.apply(inner_map.update(110, add_ctx, |r, ctx| r.write(0, ctx)));
+
+    let add_ctx = m.len().derive_add_ctx(1);
+    m.apply(m.update(101, add_ctx, |m, ctx| {
+        m.update(110, ctx, |r, ctx| r.write(0, ctx))
+    }));
+
+    assert_eq!(m.get(&101).val, Some(inner_map));
+    assert_eq!(m.len().val, 1);
+
+    m.apply(m.rm(101, m.get(&101).derive_rm_ctx()));
+
+    assert_eq!(m.get(&101).val, None);
+    assert_eq!(m.len().val, 0);
+}
+
+#[test]
+fn test_from_map() {
+    let data: BTreeMap<u8, MVReg<u8, TActor>> = (1..=3)
+        .map(|key| {
+            let mut reg = MVReg::new();
+            reg.apply(reg.write(key * 10, reg.read_ctx().derive_add_ctx(1)));
+            (key, reg)
+        })
+        .collect();
+
+    let mut m = Map::from_map(1, data.clone());
+    assert_eq!(m.len().val, 3);
+    for (key, reg) in data.iter() {
+        assert_eq!(m.get(key).val.as_ref(), Some(reg));
+        assert!(m.read_ctx().add_clock >= m.entry_clock(key).unwrap());
+    }
+    assert_eq!(m.read_ctx().add_clock, Dot::new(1, 3).into());
+
+    // the seeded map accepts further ops from the seeding actor
+    let op = m.update(4, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(40, ctx)
+    });
+    m.apply(op);
+    assert_eq!(m.len().val, 4);
+}
+
+#[test]
+fn test_retain() {
+    let mut m: Map<u8, MVReg<u8, TActor>, TActor> = Map::new();
+    for key in 1..=4 {
+        m.apply(m.update(key, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(key * 10, ctx)
+        }));
+    }
+    let clock = m.read_ctx().add_clock;
+
+    m.retain(|key, reg| key % 2 == 0 && reg.read().val != vec![40]);
+    assert_eq!(m.keys().map(|k| *k.val).collect::<Vec<_>>(), vec![2]);
+    assert_eq!(m.read_ctx().add_clock, clock);
+
+    // already seen ops are still ignored after pruning
+    let op = m.update(3, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(30, ctx)
+    });
+    m.apply(op.clone());
+    m.retain(|key, _| *key != 3);
+    m.apply(op);
+    assert_eq!(m.get(&3).val, None);
+}
+
+#[test]
+fn test_merge_with_pristine_map() {
+    let mut m: Map<u8, MVReg<u8, TActor>, TActor> = Map::new();
+    m.apply(m.update(1, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(10, ctx)
+    }));
+
+    let mut merged = m.clone();
+    merged.merge(Map::new());
+    assert_eq!(merged, m);
+
+    let mut adopted = Map::new();
+    adopted.merge(m.clone());
+    assert_eq!(adopted, m);
+}
+
+#[test]
+fn test_merge_with_dominated_clock_still_applies_removes() {
+    let mut m1: Map<u8, MVReg<u8, TActor>, TActor> = Map::new();
+    m1.apply(m1.update(1, m1.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(10, ctx)
+    }));
+    let mut m2 = m1.clone();
+    m2.apply(m2.rm(1, m2.get(&1).derive_rm_ctx()));
+
+    // removes don't advance the clock, m1's clock still dominates m2's
+    assert!(m1.read_ctx().add_clock >= m2.read_ctx().add_clock);
+    m1.merge(m2);
+    assert_eq!(m1.get(&1).val, None);
+}
+
+#[test]
+fn test_merge_lww_keeps_one_value() {
+    let mut m1: Map<u8, MVReg<u8, TActor>, TActor> = Map::new();
+    m1.apply(m1.update(1, m1.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(10, ctx)
+    }));
+    let mut m2 = m1.clone();
+
+    // actor 1 writes once, actor 2 writes twice
+    m1.apply(m1.update(1, m1.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(11, ctx)
+    }));
+    for val in [20, 21] {
+        m2.apply(m2.update(1, m2.read_ctx().derive_add_ctx(2), |reg, ctx| {
+            reg.write(val, ctx)
+        }));
+    }
+
+    let mut merged = m1.clone();
+    merged.merge(m2.clone());
+    let mut vals = merged.get(&1).val.unwrap().read().val;
+    vals.sort();
+    assert_eq!(vals, vec![11, 21]);
+
+    // entry clocks are {1: 2} and {1: 1, 2: 2}, the tie on counter 2 goes to actor 2
+    let mut lww_12 = m1.clone();
+    lww_12.merge_lww(&m2);
+    let mut lww_21 = m2.clone();
+    lww_21.merge_lww(&m1);
+    assert_eq!(lww_12, lww_21);
+    assert_eq!(lww_12.get(&1).val.unwrap().read().val, vec![21]);
+    assert_eq!(lww_12.read_ctx().add_clock, merged.read_ctx().add_clock);
+}
+
+#[test]
+fn test_collect_entries() {
+    let mut m: Map<u8, MVReg<u8, TActor>, TActor> = Map::new();
+    m.apply(m.update(1, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(10, ctx)
+    }));
+    let mut other = m.clone();
+    other.apply(
+        other.update(2, other.read_ctx().derive_add_ctx(2), |reg, ctx| {
+            reg.write(20, ctx)
+        }),
+    );
+    m.merge(other);
+    m.apply(m.update(3, m.read_ctx().derive_add_ctx(3), |reg, ctx| {
+        reg.write(30, ctx)
+    }));
+
+    let snapshot = ReadCtx::collect_entries(m.iter().filter(|ctx| *ctx.val.0 != 3));
+    assert_eq!(
+        snapshot
+            .val
+            .iter()
+            .map(|(k, reg)| (**k, reg.read().val))
+            .collect::<Vec<_>>(),
+        vec![(1, vec![10]), (2, vec![20])]
+    );
+    assert_eq!(
+        snapshot.rm_clock,
+        vec![Dot::new(1, 1), Dot::new(2, 1)].into_iter().collect()
+    );
+    assert_eq!(snapshot.add_clock, m.read_ctx().add_clock);
+
+    // removing with the snapshot context leaves the unread entry alone
+    let rm_ctx = snapshot.derive_rm_ctx();
+    for key in [1, 2, 3] {
+        m.apply(m.rm(key, rm_ctx.clone()));
+    }
+    assert_eq!(m.keys().map(|k| *k.val).collect::<Vec<_>>(), vec![3]);
+}
+
+#[test]
+fn test_compact_ops() {
+    let mut m: Map<u8, MVReg<u8, TActor>, TActor> = Map::new();
+    let up = m.update(1, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(10, ctx)
+    });
+    m.apply(up.clone());
+    let rm_ctx = m.read_ctx().derive_rm_ctx();
+    let log = vec![
+        up.clone(),
+        up,
+        m.rm(1, rm_ctx.clone()),
+        m.rm(2, rm_ctx.clone()),
+        m.rm(
+            3,
+            RmCtx {
+                clock: VClock::new(),
+            },
+        ),
+    ];
+
+    let compacted = Map::compact_ops(&log);
+    assert_eq!(compacted.len(), 2);
+    assert_eq!(
+        compacted[1],
+        map::Op::Rm {
+            clock: rm_ctx.clock,
+            keyset: vec![1, 2].into_iter().collect()
+        }
+    );
+
+    let mut m_log = Map::new();
+    log.into_iter().for_each(|op| m_log.apply(op));
+    let mut m_compacted = Map::new();
+    compacted.into_iter().for_each(|op| m_compacted.apply(op));
+    assert_eq!(m_log, m_compacted);
+}
+
+#[cfg(feature = "num")]
+#[test]
+fn test_counter_aggregates_only_count_live_entries() {
+    use crdts::GCounter;
+
+    let mut m: Map<u8, GCounter<TActor>, TActor> = Map::new();
+    assert_eq!(m.sum_counters(), 0u8.into());
+    assert_eq!(m.max_counter_value(), None);
+
+    for (key, steps) in [(1, 5), (2, 3), (3, 9)] {
+        m.apply(m.update(key, m.read_ctx().derive_add_ctx(1), |c, ctx| {
+            c.inc_many(ctx.dot.actor, steps)
+        }));
+    }
+    assert_eq!(m.sum_counters(), 17u8.into());
+    assert_eq!(m.max_counter_value(), Some(9u8.into()));
+
+    m.apply(m.rm(3, m.get(&3).derive_rm_ctx()));
+    assert_eq!(m.sum_counters(), 8u8.into());
+    assert_eq!(m.max_counter_value(), Some(5u8.into()));
+}
+
+#[test]
+fn test_try_merge_rejects_double_spent_dot() {
+    let mut m1: Map<u8, MVReg<u8, TActor>, TActor> = Map::new();
+    let mut m2 = m1.clone();
+
+    // a misbehaving peer reuses the same dot for a different key
+    m1.apply(m1.update(1, m1.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(10, ctx)
+    }));
+    m2.apply(m2.update(2, m2.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(20, ctx)
+    }));
+
+    let before = m1.clone();
+    assert_eq!(
+        m1.try_merge(m2),
+        Err(map::CvRDTValidation::DoubleSpentDot {
+            dot: Dot::new(1, 1),
+            our_key: 1,
+            their_key: 2,
+        })
+    );
+    assert_eq!(m1, before);
+
+    let mut m3: Map<u8, MVReg<u8, TActor>, TActor> = Map::new();
+    m3.apply(m3.update(3, m3.read_ctx().derive_add_ctx(3), |reg, ctx| {
+        reg.write(30, ctx)
+    }));
+    assert_eq!(m1.try_merge(m3), Ok(()));
+    assert_eq!(m1.len().val, 2);
+}
+
+#[test]
+fn test_entries_since() {
+    let mut m: Map<u8, MVReg<u8, TActor>, TActor> = Map::new();
+    for key in 1..=3 {
+        m.apply(m.update(key, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(key, ctx)
+        }));
+    }
+    let checkpoint = m.read_ctx().add_clock;
+    assert_eq!(m.entries_since(&checkpoint), vec![]);
+    assert_eq!(m.entries_since(&VClock::new()).len(), 3);
+
+    // edits from a concurrent replica and a later local edit
+    let mut other = m.clone();
+    other.apply(
+        other.update(1, other.read_ctx().derive_add_ctx(2), |reg, ctx| {
+            reg.write(11, ctx)
+        }),
+    );
+    m.apply(m.update(3, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(33, ctx)
+    }));
+    m.merge(other);
+
+    let changed: Vec<(u8, Vec<u8>)> = m
+        .entries_since(&checkpoint)
+        .into_iter()
+        .map(|(key, reg)| (key, reg.read().val))
+        .collect();
+    assert_eq!(changed, vec![(1, vec![11]), (3, vec![33])]);
+}
+
+#[test]
+fn test_reset_remove_with_own_causal_context_forgets_everything() {
+    use crdts::{CausalContext, Orswot, ResetRemove};
+
+    fn forget_all<T: CausalContext<TActor> + ResetRemove<TActor> + Default + PartialEq>(
+        mut crdt: T,
+    ) -> bool {
+        let clock = crdt.causal_context();
+        crdt.reset_remove(&clock);
+        crdt == T::default()
+    }
+
+    let mut m: Map<u8, Map<u8, MVReg<u8, TActor>, TActor>, TActor> = Map::new();
+    m.apply(m.update(1, m.read_ctx().derive_add_ctx(1), |inner, ctx| {
+        inner.update(2, ctx, |reg, ctx| reg.write(3, ctx))
+    }));
+    let inner = m.get(&1).val.unwrap();
+    assert_eq!(m.causal_context(), Dot::new(1, 1).into());
+    assert_eq!(inner.causal_context(), Dot::new(1, 1).into());
+    assert!(forget_all(inner.get(&2).val.unwrap()));
+    assert!(forget_all(inner));
+    assert!(forget_all(m));
+
+    let mut set: Orswot<u8, TActor> = Orswot::new();
+    set.apply(set.add(1, set.read_ctx().derive_add_ctx(4)));
+    set.apply(set.add(2, set.read_ctx().derive_add_ctx(5)));
+    assert_eq!(set.causal_context(), set.clock());
+    assert!(forget_all(set));
+}
+
+#[test]
+fn test_apply_observed_skips_noop_ops() {
+    let mut m: Map<TKey, TVal, TActor> = Map::new();
+    let mut seen = Vec::new();
+
+    let up = m.update(1, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(1, ctx)
+    });
+    assert!(m.apply_observed(up.clone(), |op| seen.push(op.clone())));
+    // replaying the same op is dropped by the idempotency guard
+    assert!(!m.apply_observed(up.clone(), |op| seen.push(op.clone())));
+
+    // removing a key we don't hold with a clock we've seen changes nothing
+    let missing = m.rm(2, m.get(&2).derive_rm_ctx());
+    assert!(!m.apply_observed(missing, |op| seen.push(op.clone())));
+
+    let rm = m.rm(1, m.get(&1).derive_rm_ctx());
+    assert!(m.apply_observed(rm.clone(), |op| seen.push(op.clone())));
+    assert!(!m.apply_observed(rm.clone(), |op| seen.push(op.clone())));
+
+    assert_eq!(seen, vec![up, rm]);
+}
+
+#[test]
+fn test_try_apply_reports_reused_dot() {
+    let mut m: Map<TKey, TVal, TActor> = Map::new();
+    let first = m.update(1, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(1, ctx)
+    });
+    // a second replica misconfigured with the same actor id mints the same dot
+    let twin: Map<TKey, TVal, TActor> = Map::new();
+    let second = twin.update(2, twin.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(2, ctx)
+    });
+    let other_key = m.update(2, m.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(3, ctx)
+    });
+
+    assert_eq!(m.try_apply(first.clone()), Ok(()));
+    assert_eq!(m.try_apply(other_key), Ok(()));
+    // replays are fine
+    assert_eq!(m.try_apply(first), Ok(()));
+
+    let snapshot = m.clone();
+    assert_eq!(
+        m.try_apply(second),
+        Err(map::DotConflict {
+            dot: Dot::new(1, 1),
+            key: 2
+        })
+    );
+    assert_eq!(m, snapshot);
+}
+
+#[test]
+fn test_try_apply_reports_reused_dot_on_missing_key() {
+    let mut m: Map<TKey, TVal, TActor> = Map::new();
+    let first = m.update(1, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(1, ctx)
+    });
+    // the twin mints the same dot for a key we don't hold
+    let twin: Map<TKey, TVal, TActor> = Map::new();
+    let second = twin.update(2, twin.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(2, ctx)
+    });
+    assert_eq!(m.try_apply(first.clone()), Ok(()));
+
+    let snapshot = m.clone();
+    assert_eq!(
+        m.try_apply(second),
+        Err(map::DotConflict {
+            dot: Dot::new(1, 1),
+            key: 2
+        })
+    );
+    assert_eq!(m, snapshot);
+
+    // a replay of an update that was since removed can't be told apart
+    m.apply(m.rm(1, m.get(&1).derive_rm_ctx()));
+    assert_eq!(
+        m.try_apply(first),
+        Err(map::DotConflict {
+            dot: Dot::new(1, 1),
+            key: 1
+        })
+    );
+}
+
+#[test]
+fn test_range_bounds_and_removed_keys() {
+    let mut m: Map<TKey, TVal, TActor> = Map::new();
+    for key in [1, 3, 5, 7, 9] {
+        m.apply(m.update(key, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(key, ctx)
+        }));
+    }
+    m.apply(m.rm(5, m.get(&5).derive_rm_ctx()));
+
+    let keys = |r: Vec<ReadCtx<(&TKey, &TVal), TActor>>| -> Vec<TKey> {
+        r.into_iter().map(|ctx| *ctx.val.0).collect()
+    };
+    assert_eq!(keys(m.range(3..7).collect()), vec![3]);
+    assert_eq!(keys(m.range(3..=7).collect()), vec![3, 7]);
+    assert_eq!(keys(m.range(..4).collect()), vec![1, 3]);
+    assert_eq!(keys(m.range(8..).collect()), vec![9]);
+    assert_eq!(keys(m.range(4..5).collect()), Vec::<TKey>::new());
+
+    let ctx = m.range(7..8).next().unwrap();
+    assert_eq!(ctx.val.1.read().val, vec![7]);
+    assert_eq!(ctx.rm_clock, m.entry_clock(&7).unwrap());
+}
+
+#[test]
+fn test_conflicts_lists_concurrent_writes() {
+    let mut a: Map<TKey, TVal, TActor> = Map::new();
+    a.apply(a.update(1, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(1, ctx)
+    }));
+    a.apply(a.update(2, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(2, ctx)
+    }));
+    assert!(a.conflicts().is_empty());
+
+    let mut b = a.clone();
+    a.apply(a.update(2, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(3, ctx)
+    }));
+    b.apply(b.update(2, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(4, ctx)
+    }));
+    a.merge(b);
+
+    assert_eq!(a.conflicts(), vec![(2, 2)]);
+}
+
+#[test]
+fn test_update_many_converges_with_concurrent_ops() {
+    let base: Map<TKey, TVal, TActor> = Map::new();
+    let mut a = base.clone();
+    let mut b = base.clone();
+
+    let many = a.update_many(
+        vec![1, 2, 1],
+        a.read_ctx().derive_add_ctx(1),
+        |key, reg, ctx| reg.write(*key * 10, ctx),
+    );
+    let single = b.update(2, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(99, ctx)
+    });
+    a.apply(many.clone());
+    b.apply(single.clone());
+
+    // the duplicate key was skipped and each key got a dot of its own
+    assert_eq!(a.read_ctx().add_clock, VClock::from(Dot::new(1, 2)));
+    assert_eq!(a.entry_clock(&2), Some(VClock::from(Dot::new(1, 2))));
+
+    let mut op_ab = base.clone();
+    op_ab.apply(many.clone());
+    op_ab.apply(single.clone());
+    op_ab.apply(many.clone());
+    let mut op_ba = base;
+    op_ba.apply(single);
+    op_ba.apply(many);
+
+    assert_eq!(a.validate_merge(&b), Ok(()));
+    let mut ab = a.clone();
+    ab.merge(b.clone());
+    let mut ba = b;
+    ba.merge(a);
+
+    assert_eq!(ab, ba);
+    assert_eq!(ab, op_ab);
+    assert_eq!(ab, op_ba);
+    assert_eq!(ab.get(&1).val.unwrap().read().val, vec![10]);
+    assert_eq!(ab.get(&2).val.unwrap().read().val, vec![20, 99]);
+}
+
+#[test]
+fn test_merge_with_progress_reports_entries() {
+    let seed = |actor: TActor, keys: std::ops::Range<u32>| -> Map<u32, TVal, TActor> {
+        Map::from_map(actor, keys.map(|k| (k, MVReg::new())).collect())
+    };
+    let a = seed(1, 0..5000);
+    let b = seed(2, 2500..7500);
+
+    let mut calls = Vec::new();
+    let mut with_progress = a.clone();
+    with_progress.merge_with_progress(b.clone(), |processed, total| calls.push((processed, total)));
+    let mut merged = a;
+    merged.merge(b);
+
+    assert_eq!(with_progress, merged);
+    assert_eq!(calls, vec![(4096, 10000), (8192, 10000), (10000, 10000)]);
+}
+
+#[test]
+fn test_op_batch_factors_out_actor() {
+    type Reg = MVReg<u8, String>;
+    let mut m: Map<u8, Reg, String> = Map::new();
+    let mut ops = Vec::new();
+    for key in 0..20 {
+        let ctx = m
+            .read_ctx()
+            .derive_add_ctx("a-rather-long-actor-id".to_string());
+        let op = m.update(key, ctx, |reg, ctx| reg.write(key, ctx));
+        m.apply(op.clone());
+        ops.push(op);
+    }
+    let batch = map::OpBatch::from(ops.clone());
+
+    let batch_json = serde_json::to_string(&batch).unwrap();
+    let ops_json = serde_json::to_string(&ops).unwrap();
+    assert!(batch_json.len() < ops_json.len());
+
+    // ops from other actors and removes survive the round trip as well
+    ops.push(m.rm(3, m.get(&3).derive_rm_ctx()));
+    let other = Map::<u8, Reg, String>::new();
+    ops.push(other.update(
+        1,
+        other.read_ctx().derive_add_ctx("b".to_string()),
+        |reg, ctx| reg.write(9, ctx),
+    ));
+    let batch = map::OpBatch::from(ops.clone());
+    let decoded: map::OpBatch<u8, Reg, String> =
+        serde_json::from_str(&serde_json::to_string(&batch).unwrap()).unwrap();
+    assert_eq!(decoded, batch);
+    assert_eq!(decoded.into_ops(), ops);
+}
+
+#[test]
+#[should_panic(expected = "no entry found for key")]
+fn test_index_panics_on_missing_key() {
+    let m: Map<TKey, TVal, TActor> = Map::new();
+    let _ = &m[&1];
+}
+
+#[test]
+fn test_assert_merge_laws() {
+    let mut a: Map<TKey, TVal, TActor> = Map::new();
+    a.apply(a.update(1, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(1, ctx)
+    }));
+    let mut b = a.clone();
+    let mut c = a.clone();
+    a.apply(a.update(2, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(2, ctx)
+    }));
+    b.apply(b.rm(1, b.get(&1).derive_rm_ctx()));
+    c.apply(c.update(1, c.read_ctx().derive_add_ctx(3), |reg, ctx| {
+        reg.write(3, ctx)
+    }));
+
+    Map::assert_merge_commutative(&a, &b);
+    Map::assert_merge_commutative(&b, &c);
+    Map::assert_merge_associative(&a, &b, &c);
+}
+
+#[test]
+fn test_update_init_only_inits_absent_keys() {
+    let mut m: Map<TKey, TVal, TActor> = Map::new();
+    let seeded = || {
+        let mut reg = MVReg::new();
+        reg.apply(reg.write(40, reg.read_ctx().derive_add_ctx(0)));
+        reg
+    };
+    let bump = |reg: &TVal, ctx| reg.write(reg.read().val.iter().sum::<u8>() + 2, ctx);
+
+    m.apply(m.update_init(1, m.read_ctx().derive_add_ctx(1), seeded, bump));
+    assert_eq!(m.get(&1).val.unwrap().read().val, vec![42]);
+
+    let op = m.update_init(
+        1,
+        m.read_ctx().derive_add_ctx(1),
+        || panic!("key 1 is present"),
+        bump,
+    );
+    m.apply(op);
+    assert_eq!(m.get(&1).val.unwrap().read().val, vec![44]);
+}
+
+#[test]
+fn test_sync_ships_nested_removes_of_seen_entries() {
+    use crdts::Orswot;
+
+    let mut a: Map<&str, Orswot<u8, u8>, u8> = Map::new();
+    a.apply(a.update("k", a.read_ctx().derive_add_ctx(1), |set, ctx| {
+        set.add(1, ctx)
+    }));
+    let rm_ctx = a.get(&"k").derive_rm_ctx();
+    a.apply(a.update("k", a.read_ctx().derive_add_ctx(1), |set, ctx| {
+        set.add(2, ctx)
+    }));
+    let mut b = a.clone();
+    // b has seen the entry's clock but not this remove of its older dot
+    a.apply(a.rm("k", rm_ctx));
+
+    b.sync_apply(a.sync_payload(&b.read_ctx().add_clock));
+    assert_eq!(b.get(&"k").val.unwrap().read().val, [2].into());
+    assert!(a.logically_eq(&b));
+}
+
+#[test]
+fn test_two_peer_sync_round_converges() {
+    let mut a: Map<TKey, TVal, TActor> = Map::new();
+    for key in 0..4 {
+        a.apply(a.update(key, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(key, ctx)
+        }));
+    }
+    let mut b = a.clone();
+
+    // both peers keep editing while disconnected
+    a.apply(a.update(1, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(10, ctx)
+    }));
+    a.apply(a.rm(2, a.get(&2).derive_rm_ctx()));
+    b.apply(b.update(1, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(20, ctx)
+    }));
+    b.apply(b.update(5, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(5, ctx)
+    }));
+    b.apply(b.rm(3, b.get(&3).derive_rm_ctx()));
+
+    let mut merged = a.clone();
+    merged.merge(b.clone());
+
+    // exchange clocks, then payloads
+    let a_clock = a.read_ctx().add_clock;
+    let b_clock = b.read_ctx().add_clock;
+    let for_a = b.sync_payload(&a_clock);
+    let for_b = a.sync_payload(&b_clock);
+    a.sync_apply(for_a);
+    b.sync_apply(for_b);
+
+    assert_eq!(a, b);
+    assert_eq!(a, merged);
+    assert_eq!(
+        a.keys().map(|ctx| *ctx.val).collect::<Vec<_>>(),
+        vec![0, 1, 5]
+    );
+}
+
+#[test]
+fn test_shrink_to_fit_keeps_state() {
+    let mut m: Map<u32, TVal, TActor> = Map::new();
+    // removes of adds we haven't seen yet pile up as deferred removes
+    for counter in 1..=200u64 {
+        let clock = VClock::from(Dot::new(2, counter));
+        m.apply(m.rm(counter as u32, RmCtx { clock }));
+    }
+    for key in 0..200u32 {
+        m.apply(m.update(key, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(1, ctx)
+        }));
+    }
+    // catching up with actor 2 applies and clears the deferred removes
+    let mut peer: Map<u32, TVal, TActor> = Map::new();
+    for key in 1000..1200u32 {
+        peer.apply(
+            peer.update(key, peer.read_ctx().derive_add_ctx(2), |reg, ctx| {
+                reg.write(2, ctx)
+            }),
+        );
+    }
+    m.merge(peer);
+
+    let before = m.clone();
+    m.shrink_to_fit();
+    assert_eq!(m, before);
+
+    let edit = |m: &Map<u32, TVal, TActor>| {
+        m.update(7u32, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(3, ctx)
+        })
+    };
+    let mut expected = before;
+    m.apply(edit(&m));
+    expected.apply(edit(&expected));
+    assert_eq!(m, expected);
+}
+
+#[test]
+fn test_get_ref_matches_get() {
+    let mut m: Map<TKey, TVal, TActor> = Map::new();
+    m.apply(m.update(1, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(1, ctx)
+    }));
+    m.apply(m.update(2, m.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(2, ctx)
+    }));
+
+    for key in [1, 2, 3] {
+        let owned = m.get(&key);
+        let borrowed = m.get_ref(&key);
+        assert_eq!(borrowed.add_clock, owned.add_clock);
+        assert_eq!(borrowed.rm_clock, owned.rm_clock);
+        assert_eq!(borrowed.val, owned.val.as_ref());
+    }
+}
+
+#[test]
+fn test_truncate_returning_splits_state() {
+    use crdts::ResetRemove;
+
+    let mut m: Map<TKey, TVal, TActor> = Map::new();
+    m.apply(m.update(1, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(1, ctx)
+    }));
+    m.apply(m.update(2, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(2, ctx)
+    }));
+    m.apply(m.update(2, m.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(3, ctx)
+    }));
+
+    let clock = VClock::from(Dot::new(1, 2));
+    let mut expected = m.clone();
+    expected.reset_remove(&clock);
+
+    let removed = m.truncate_returning(&clock);
+    assert_eq!(m, expected);
+    assert_eq!(m.get(&1).val, None);
+    assert_eq!(m.entry_clock(&2), Some(Dot::new(2, 1).into()));
+
+    // key 1 was forgotten outright, key 2 lost the dot from actor 1
+    assert_eq!(removed.get(&1).val.map(|reg| reg.read().val), Some(vec![1]));
+    assert_eq!(removed.entry_clock(&1), Some(Dot::new(1, 1).into()));
+    assert_eq!(removed.entry_clock(&2), Some(Dot::new(1, 2).into()));
+    assert_eq!(removed.read_ctx().add_clock, clock);
+
+    // truncating by a clock that covers nothing returns an empty map
+    assert!(m.truncate_returning(&VClock::new()).is_default());
+}
+
+#[test]
+fn test_independently_created_keys() {
+    let mut a: Map<TKey, TVal, TActor> = Map::new();
+    let mut b: Map<TKey, TVal, TActor> = Map::new();
+    a.apply(a.update(1, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(1, ctx)
+    }));
+    b.merge(a.clone());
+
+    // both sides edit key 1 after syncing, but create key 2 on their own
+    a.apply(a.update(1, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(2, ctx)
+    }));
+    b.apply(b.update(1, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(3, ctx)
+    }));
+    a.apply(a.update(2, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(4, ctx)
+    }));
+    b.apply(b.update(2, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(5, ctx)
+    }));
+    b.apply(b.update(3, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(6, ctx)
+    }));
+
+    assert_eq!(a.independently_created_keys(&b), vec![2]);
+    assert_eq!(b.independently_created_keys(&a), vec![2]);
+
+    a.merge(b.clone());
+    assert!(a.independently_created_keys(&b).is_empty());
+}
+
+#[test]
+fn test_merge_of_mostly_synced_maps() {
+    let mut a: Map<TKey, TVal, TActor> = Map::new();
+    for key in 0..100 {
+        a.apply(a.update(key, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(key, ctx)
+        }));
+    }
+    let mut b = a.clone();
+    b.apply(b.update(7, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(0, ctx)
+    }));
+    b.apply(b.rm(8, b.get(&8).derive_rm_ctx()));
+
+    let mut merged = a.clone();
+    merged.merge(b.clone());
+    assert_eq!(merged, b);
+    assert_eq!(merged, b.merged_with(&a));
+    assert_eq!(merged.get(&7).val.unwrap().read().val, vec![0]);
+    assert_eq!(merged.get(&9).val.unwrap().read().val, vec![9]);
+}
+
+#[test]
+fn test_heap_size_grows_with_entries() {
+    use crdts::HeapSize;
+
+    let mut m: Map<u32, TVal, TActor> = Map::new();
+    let empty = m.heap_size();
+    let mut sizes = Vec::new();
+    for key in 0..300u32 {
+        m.apply(m.update(key, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(1, ctx)
+        }));
+        if (key + 1) % 100 == 0 {
+            sizes.push(m.heap_size() - empty);
+        }
+    }
+    // past the first entry, which also adds the actor to the map clock,
+    // every entry adds the same amount
+    assert!(sizes[0] > 0);
+    assert!(sizes[1] - sizes[0] > 0);
+    assert_eq!(sizes[2] - sizes[1], sizes[1] - sizes[0]);
+
+    for key in 0..300u32 {
+        m.apply(m.rm(key, m.get(&key).derive_rm_ctx()));
+    }
+    assert_eq!(m.heap_size(), m.read_ctx().add_clock.heap_size());
+}
+
+#[test]
+fn test_extract_and_rejoin_is_lossless() {
+    let mut m: Map<TKey, TVal, TActor> = Map::new();
+    for key in 0..10 {
+        m.apply(m.update(key, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(key, ctx)
+        }));
+    }
+    // removes of adds from actor 2 we haven't seen yet, one on each side
+    m.apply(m.rm(
+        3,
+        RmCtx {
+            clock: Dot::new(2, 1).into(),
+        },
+    ));
+    m.apply(m.rm(
+        8,
+        RmCtx {
+            clock: Dot::new(2, 2).into(),
+        },
+    ));
+    let original = m.clone();
+
+    let shard = m.extract(5..);
+    assert_eq!(shard.len().val, 5);
+    assert_eq!(m.len().val, 5);
+    assert!(m.get(&7).val.is_none());
+    assert_eq!(shard.get(&7).val.unwrap().read().val, vec![7]);
+
+    m.rejoin(shard);
+    assert_eq!(m, original);
+}
+
+#[test]
+fn test_rejoin_applies_removes_deferred_by_either_side() {
+    let mut m: Map<TKey, TVal, TActor> = Map::new();
+    for key in 0..10 {
+        m.apply(m.update(key, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(key, ctx)
+        }));
+    }
+    let write = m.update(12, m.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(12, ctx)
+    });
+    let rm = m.rm(
+        12,
+        RmCtx {
+            clock: Dot::new(2, 1).into(),
+        },
+    );
+    let mut expected = m.clone();
+    expected.apply(write.clone());
+    expected.apply(rm.clone());
+    assert!(expected.get(&12).val.is_none());
+
+    let mut shard = m.extract(5..);
+    // the remove reaches the map without the key, the write reaches the shard
+    m.apply(rm);
+    shard.apply(write);
+    assert_eq!(shard.get(&12).val.unwrap().read().val, vec![12]);
+
+    m.rejoin(shard);
+    assert!(m.get(&12).val.is_none());
+    assert_eq!(m, expected);
+}
+
+#[test]
+fn test_op_recorder_log_replays_to_same_state() {
+    let mut m = Map::<TKey, TVal, TActor>::with_op_recorder();
+    m.apply(m.update(1, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(1, ctx)
+    }));
+    m.apply(m.update(2, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(2, ctx)
+    }));
+    let stale = m.update(3, m.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(3, ctx)
+    });
+    m.apply(stale.clone());
+    m.apply(m.rm(1, m.get(&1).derive_rm_ctx()));
+
+    // neither a replayed op nor a remove of a missing key changes the map
+    m.apply(stale);
+    m.apply(m.rm(9, m.get(&9).derive_rm_ctx()));
+    assert_eq!(m.log().len(), 4);
+
+    let mut replayed: Map<TKey, TVal, TActor> = Map::new();
+    for op in m.take_log() {
+        replayed.apply(op);
+    }
+    assert!(m.log().is_empty());
+    assert_eq!(replayed, m.into_map());
+}
+
+#[test]
+fn test_logically_eq_ignores_history() {
+    let mut a: Map<TKey, TVal, TActor> = Map::new();
+    a.apply(a.update(1, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(1, ctx)
+    }));
+
+    // b saw a key come and go, and holds a remove it can't apply yet
+    let mut b = a.clone();
+    b.apply(b.update(2, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(2, ctx)
+    }));
+    b.apply(b.rm(2, b.get(&2).derive_rm_ctx()));
+    b.apply(b.rm(
+        1,
+        RmCtx {
+            clock: Dot::new(3, 1).into(),
+        },
+    ));
+
+    assert_ne!(a, b);
+    assert!(a.logically_eq(&b));
+    assert!(b.logically_eq(&a));
+
+    b.apply(b.update(1, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(3, ctx)
+    }));
+    assert!(!a.logically_eq(&b));
+}
+
+#[test]
+fn test_logically_eq_ignores_nested_history() {
+    use crdts::Orswot;
+
+    let mut a: Map<u8, Orswot<u8, u8>, u8> = Map::new();
+    a.apply(a.update(1, a.read_ctx().derive_add_ctx(1), |set, ctx| {
+        set.add(1, ctx)
+    }));
+
+    // b saw a member come and go, so its set has a clock a's doesn't
+    let mut b = a.clone();
+    b.apply(b.update(1, b.read_ctx().derive_add_ctx(2), |set, ctx| {
+        set.add(2, ctx)
+    }));
+    b.apply(b.update(1, b.read_ctx().derive_add_ctx(2), |set, _| {
+        set.rm(2, set.contains(&2).derive_rm_ctx())
+    }));
+
+    assert_ne!(a.get(&1).val, b.get(&1).val);
+    assert!(a.logically_eq(&b));
+
+    b.apply(b.update(1, b.read_ctx().derive_add_ctx(2), |set, ctx| {
+        set.add(3, ctx)
+    }));
+    assert!(!a.logically_eq(&b));
 }
 
 #[test]
-fn test_is_empty() {
-    let mut m: Map<&str, Map<&str, MVReg<&str, _>, _>, &str> = Map::new();
-    let is_empty_read = m.is_empty();
-    assert!(is_empty_read.val);
+fn test_op_pretty_renders_nested_tree() {
+    use crdts::Orswot;
 
-    m.apply(
-        m.update("user_32", is_empty_read.derive_add_ctx("A"), |map, ctx| {
-            map.update("name", ctx, |reg, ctx| reg.write("bob", ctx))
-        }),
+    let m: Map<u8, Map<u8, Orswot<u8, u8>, u8>, u8> = Map::new();
+    let ctx = m.read_ctx().derive_add_ctx(1);
+    let op = m.update_many([1, 2], ctx, |_, inner, ctx| {
+        inner.update(3, ctx, |set, ctx| set.add(4, ctx))
+    });
+    assert_eq!(
+        op.pretty(),
+        "UpMany 1.1\n  1\n    Up 1.1 3\n      Add(1.1, [4])\n  2\n    Up 1.2 3\n      Add(1.2, [4])"
     );
 
-    assert!(!m.is_empty().val);
+    // the plain Debug format stays on one line
+    assert!(!format!("{:?}", op).contains('\n'));
 }
 
 #[test]
-fn test_update() {
-    let mut m: TMap = Map::new();
+fn test_apply_checked_reports_changes() {
+    let mut m: Map<TKey, TVal, TActor> = Map::new();
+    let op = m.update(1, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(1, ctx)
+    });
+    assert!(m.apply_checked(&op));
+    assert!(!m.apply_checked(&op));
 
-    // constructs a default value if does not exist
-    let ctx = m.get(&101).derive_add_ctx(1);
-    let op = m.update(101, ctx, |map, ctx| {
-        map.update(110, ctx, |reg, ctx| reg.write(2, ctx))
+    let rm = m.rm(1, m.get(&1).derive_rm_ctx());
+    assert!(m.apply_checked(&rm));
+    assert!(!m.apply_checked(&rm));
+    assert_eq!(m.get(&1).val, None);
+}
+
+#[test]
+fn test_merge_with_stats_counts_each_decision() {
+    use crdts::map::MergeStats;
+
+    let mut a: Map<TKey, TVal, TActor> = Map::new();
+    for key in [1, 2, 3] {
+        a.apply(a.update(key, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(key, ctx)
+        }));
+    }
+    let mut b = a.clone();
+
+    // 4 is only written at a, 5 only at b
+    a.apply(a.update(4, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(4, ctx)
+    }));
+    b.apply(b.update(5, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(5, ctx)
+    }));
+    // 2 is rewritten at b and removed at b
+    b.apply(b.update(2, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(20, ctx)
+    }));
+    b.apply(b.rm(1, b.get(&1).derive_rm_ctx()));
+    // b holds a remove of a write it hasn't seen yet
+    let mut unseen = VClock::new();
+    unseen.apply(Dot::new(3, 1));
+    b.apply(map::Op::Rm {
+        clock: unseen,
+        keyset: [9].into_iter().collect(),
     });
 
+    let mut expected = a.clone();
+    expected.merge(b.clone());
+
+    let stats = a.merge_with_stats(b);
+    assert_eq!(a, expected);
     assert_eq!(
-        op,
-        map::Op::Up {
-            dot: Dot::new(1, 1),
-            key: 101,
-            op: map::Op::Up {
-                dot: Dot::new(1, 1),
-                key: 110,
-                op: mvreg::Op::Put {
-                    clock: Dot::new(1, 1).into(),
-                    val: 2
-                }
-            }
+        stats,
+        MergeStats {
+            added: 1,
+            kept: 1,
+            dropped: 1,
+            merged: 1,
+            unchanged: 1,
+            deferred: 1,
         }
     );
+}
 
-    // update should not mutate the map
-    assert_eq!(m, Map::new());
+#[test]
+fn test_update_path_reports_missing_level() {
+    use crdts::map::PathError;
 
-    m.apply(op);
+    type Inner = Map<TKey, TVal, TActor>;
+    let mut m: Map<TKey, Map<TKey, Inner, TActor>, TActor> = Map::new();
+    let write = |val: u8| move |reg: &TVal, ctx| reg.write(val, ctx);
 
+    let ctx = m.read_ctx().derive_add_ctx(1);
     assert_eq!(
-        m.get(&101)
-            .val
-            .and_then(|m2| m2.get(&110).val)
-            .map(|r| r.read().val),
-        Some(vec![2])
+        m.update_path(&[1, 2, 3], ctx, write(7)),
+        Err(PathError::MissingLevel(0))
     );
 
-    // the map should give the latest val to the closure
-    m.apply(m.update(101, m.get(&101).derive_add_ctx(1), |map, ctx| {
-        map.update(110, ctx, |reg, ctx| {
-            assert_eq!(reg.read().val, vec![2]);
-            reg.write(6, ctx)
+    m.apply(m.update(1, m.read_ctx().derive_add_ctx(1), |outer, ctx| {
+        outer.update(9, ctx, |inner, ctx| {
+            inner.update(3, ctx, |reg, ctx| reg.write(0, ctx))
         })
     }));
+    let ctx = m.read_ctx().derive_add_ctx(1);
+    assert_eq!(
+        m.update_path(&[1, 2, 3], ctx, write(7)),
+        Err(PathError::MissingLevel(1))
+    );
+
+    // the leaf itself may be missing
+    let ctx = m.read_ctx().derive_add_ctx(1);
+    m.apply(m.update_path(&[1, 9, 4], ctx, write(7)).unwrap());
+    let inner = m.get(&1).val.unwrap().get(&9).val.unwrap();
+    assert_eq!(inner.get(&4).val.unwrap().read().val, vec![7]);
+    assert_eq!(inner.get(&3).val.unwrap().read().val, vec![0]);
+}
+
+#[test]
+fn test_expire_removes_stale_entries() {
+    use crdts::lwwmap::Register;
+
+    let mut a: Map<TKey, Register<u8, u64, TActor>, TActor> = Map::new();
+    for (key, written_at) in [(1, 100), (2, 150), (3, 190), (4, 200)] {
+        a.apply(a.update(key, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(key, written_at, ctx)
+        }));
+    }
+    let mut b = a.clone();
 
+    let expired = a.expire(200, 40);
     assert_eq!(
-        m.get(&101)
-            .val
-            .and_then(|m2| m2.get(&110).val)
-            .map(|r| r.read().val),
-        Some(vec![6])
+        expired,
+        vec![
+            a.rm(1, a.get(&1).derive_rm_ctx()),
+            a.rm(2, a.get(&2).derive_rm_ctx())
+        ]
     );
+
+    // b refreshes 2 concurrently with the expiry, so it stays
+    b.apply(b.update(2, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(2, 210, ctx)
+    }));
+    for op in expired {
+        a.apply(op);
+    }
+    a.merge(b);
+    assert_eq!(a.keys().map(|k| *k.val).collect::<Vec<_>>(), vec![2, 3, 4]);
+    assert!(a.expire(200, 40).is_empty());
 }
 
 #[test]
-fn test_remove() {
-    let mut m: TMap = Map::new();
+fn test_entries_by_actor() {
+    let mut a: Map<TKey, TVal, TActor> = Map::new();
+    for key in [1, 2, 3] {
+        a.apply(a.update(key, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(key, ctx)
+        }));
+    }
+    let mut b = a.clone();
+    for key in [3, 4] {
+        b.apply(b.update(key, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+            reg.write(key * 10, ctx)
+        }));
+    }
+    a.merge(b);
 
-    let add_ctx = m.len().derive_add_ctx(1);
-    let mut inner_map: Map<TKey, TVal, TActor> = Map::new();
-    inner_map.apply(inner_map.update(110, add_ctx, |r, ctx| r.write(0, ctx)));
+    let by = |m: &Map<TKey, TVal, TActor>, actor| {
+        m.entries_by_actor(&actor)
+            .map(|(k, v)| (*k, v.read().val))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(by(&a, 1), vec![(1, vec![1]), (2, vec![2]), (3, vec![30])]);
+    assert_eq!(by(&a, 2), vec![(3, vec![30]), (4, vec![40])]);
+    assert_eq!(by(&a, 3), vec![]);
+
+    // a remove resets the entry clock, forgetting who wrote to it
+    a.apply(a.rm(3, a.get(&3).derive_rm_ctx()));
+    a.apply(a.update(3, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(3, ctx)
+    }));
+    assert_eq!(by(&a, 2), vec![(4, vec![40])]);
+}
 
-    let add_ctx = m.len().derive_add_ctx(1);
-    m.apply(m.update(101, add_ctx, |m, ctx| {
-        m.update(110, ctx, |r, ctx| r.write(0, ctx))
+#[test]
+fn test_update_if_unchanged_refuses_after_write() {
+    let mut a: Map<TKey, TVal, TActor> = Map::new();
+    a.apply(a.update(1, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(1, ctx)
     }));
+    let mut b = a.clone();
+    let expected = a.get(&1).rm_clock;
 
-    assert_eq!(m.get(&101).val, Some(inner_map));
-    assert_eq!(m.len().val, 1);
+    // a write at b reaches a before a's compare-and-swap
+    b.apply(b.update(1, b.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(2, ctx)
+    }));
+    a.merge(b);
+    assert_eq!(
+        a.update_if_unchanged(1, expected, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(3, ctx)
+        }),
+        None
+    );
 
-    m.apply(m.rm(101, m.get(&101).derive_rm_ctx()));
+    // retrying with a fresh read succeeds
+    let expected = a.get(&1).rm_clock;
+    let op = a
+        .update_if_unchanged(1, expected, a.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(3, ctx)
+        })
+        .unwrap();
+    a.apply(op);
+    assert_eq!(a.get(&1).val.unwrap().read().val, vec![3]);
+
+    // an absent entry is expected to have an empty clock
+    assert!(a
+        .update_if_unchanged(
+            2,
+            VClock::new(),
+            a.read_ctx().derive_add_ctx(1),
+            |reg, ctx| { reg.write(4, ctx) }
+        )
+        .is_some());
+}
 
-    assert_eq!(m.get(&101).val, None);
-    assert_eq!(m.len().val, 0);
+#[test]
+fn test_reset_remove_settles_nested_deferred_removes() {
+    type Inner = Map<TKey, TVal, TActor>;
+    let mut m: Map<TKey, Inner, TActor> = Map::new();
+    m.apply(m.update(0, m.read_ctx().derive_add_ctx(2), |inner, ctx| {
+        inner.update(1, ctx, |reg, ctx| reg.write(1, ctx))
+    }));
+
+    // the inner map defers a remove that has seen writes from 1 it hasn't
+    let mut rm_clock = VClock::from_pairs(vec![(1, 5)]);
+    rm_clock.apply(Dot::new(2, 1));
+    m.apply(
+        m.update(0, m.read_ctx().derive_add_ctx(2), |_, _| map::Op::Rm {
+            clock: rm_clock,
+            keyset: [1].into_iter().collect(),
+        }),
+    );
+
+    // a remove of the outer entry that doesn't cover all of it
+    m.apply(m.rm(
+        0,
+        RmCtx {
+            clock: VClock::from_pairs(vec![(1, 5)]),
+        },
+    ));
+    let inner = m.get(&0).val.unwrap();
+    assert_eq!(inner.check_invariants(), Ok(()));
+
+    // the inner map keeps taking updates
+    m.apply(m.update(0, m.read_ctx().derive_add_ctx(2), |inner, ctx| {
+        inner.update(2, ctx, |reg, ctx| reg.write(2, ctx))
+    }));
+    assert_eq!(m.check_invariants(), Ok(()));
+    let inner = m.get(&0).val.unwrap();
+    assert_eq!(inner.check_invariants(), Ok(()));
+    assert_eq!(inner.get(&2).val.unwrap().read().val, vec![2]);
+}
+
+#[test]
+fn test_merge_of_equal_entry_clocks_merges_nested_removes() {
+    use crdts::Orswot;
+
+    let mut a: Map<&str, Orswot<u8, u8>, u8> = Map::new();
+    a.apply(a.update("k", a.read_ctx().derive_add_ctx(1), |set, ctx| {
+        set.add(1, ctx)
+    }));
+    let rm_ctx = a.get(&"k").derive_rm_ctx();
+    a.apply(a.update("k", a.read_ctx().derive_add_ctx(1), |set, ctx| {
+        set.add(2, ctx)
+    }));
+    let b = a.clone();
+
+    // the remove drops the older nested dot and leaves the entry clock as is
+    a.apply(a.rm("k", rm_ctx));
+    assert_eq!(a.get(&"k").val.unwrap().read().val, [2].into());
+
+    let ab = a.merged_with(&b);
+    let ba = b.merged_with(&a);
+    assert_eq!(ab, ba);
+    assert_eq!(ab.get(&"k").val.unwrap().read().val, [2].into());
+}
+
+#[test]
+fn test_is_default() {
+    let mut m: Map<u8, MVReg<u8, TActor>, TActor> = Map::new();
+    assert!(m.is_default());
+
+    m.apply(m.update(1, m.get(&1).derive_add_ctx(1), |reg, ctx| {
+        reg.write(10, ctx)
+    }));
+    assert!(!m.is_default());
+    assert!(!m.get(&1).val.unwrap().is_default());
+
+    // removing every entry empties the map but its clock keeps the history
+    m.apply(m.rm(1, m.get(&1).derive_rm_ctx()));
+    assert!(m.is_empty().val);
+    assert!(!m.is_default());
+    assert!(MVReg::<u8, TActor>::new().is_default());
+}
+
+#[test]
+fn test_tombstone_log() {
+    let mut m: Map<u8, MVReg<u8, TActor>, TActor> = Map::with_tombstone_log(2);
+    let mut ops = vec![];
+    for key in 1..=3 {
+        let op = m.update(key, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(key, ctx)
+        });
+        m.apply(op.clone());
+        ops.push(op);
+    }
+    assert_eq!(m.tombstones().count(), 0);
+
+    let rm_ctx = m.get(&1).derive_rm_ctx();
+    let op = m.rm(1, rm_ctx.clone());
+    m.apply(op.clone());
+    ops.push(op);
+    assert_eq!(m.tombstones().collect::<Vec<_>>(), vec![&(1, rm_ctx.clock)]);
+
+    // the log is bounded, the oldest removes are dropped first
+    for key in 2..=3 {
+        let op = m.rm(key, m.get(&key).derive_rm_ctx());
+        m.apply(op.clone());
+        ops.push(op);
+    }
+    assert_eq!(
+        m.tombstones().map(|(k, _)| *k).collect::<Vec<_>>(),
+        vec![2, 3]
+    );
+
+    // the log is local metadata and doesn't affect equality
+    let mut plain = Map::new();
+    ops.into_iter().for_each(|op| plain.apply(op));
+    assert_eq!(plain.tombstones().count(), 0);
+    assert_eq!(m, plain);
+}
+
+#[test]
+fn test_tombstone_log_records_deferred_removes() {
+    let mut m1: Map<u8, MVReg<u8, TActor>, TActor> = Map::new();
+    let op = m1.update(1, m1.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(10, ctx)
+    });
+    m1.apply(op.clone());
+    let rm_op = m1.rm(1, m1.get(&1).derive_rm_ctx());
+
+    // the remove arrives before the update it removes
+    let mut m2: Map<u8, MVReg<u8, TActor>, TActor> = Map::with_tombstone_log(10);
+    m2.apply(rm_op);
+    assert_eq!(m2.tombstones().count(), 0);
+
+    m2.apply(op);
+    assert_eq!(m2.get(&1).val, None);
+    assert_eq!(
+        m2.tombstones().collect::<Vec<_>>(),
+        vec![&(1, Dot::new(1, 1).into())]
+    );
+}
+
+#[test]
+fn test_deferred_limit_evicts_oldest() {
+    // removes from three replicas that have each seen an update we haven't
+    let mut ups = vec![];
+    let mut rms = vec![];
+    for actor in 1..=3 {
+        let mut peer: Map<u8, MVReg<u8, TActor>, TActor> = Map::new();
+        let up = peer.update(actor, peer.read_ctx().derive_add_ctx(actor), |reg, ctx| {
+            reg.write(actor, ctx)
+        });
+        peer.apply(up.clone());
+        rms.push(peer.rm(actor, peer.get(&actor).derive_rm_ctx()));
+        ups.push(up);
+    }
+
+    let mut m: Map<u8, MVReg<u8, TActor>, TActor> = Map::with_deferred_limit(2);
+    rms.into_iter().for_each(|op| m.apply(op));
+    assert_eq!(m.evicted_deferred(), 1);
+
+    // the oldest remove was dropped so its key survives, the others are removed
+    ups.into_iter().for_each(|op| m.apply(op));
+    assert_eq!(m.keys().map(|k| *k.val).collect::<Vec<_>>(), vec![1]);
+    assert_eq!(m.evicted_deferred(), 1);
+    assert_eq!(
+        Map::<u8, MVReg<u8, TActor>, TActor>::new().evicted_deferred(),
+        0
+    );
+}
+
+#[test]
+fn test_capacity_policy_evicts_oldest() {
+    use crdts::map::EvictionPolicy;
+
+    fn write(m: &mut Map<u8, MVReg<u8, TActor>, TActor>, key: u8) {
+        m.apply(m.update(key, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+            reg.write(key, ctx)
+        }));
+    }
+    fn keys(m: &Map<u8, MVReg<u8, TActor>, TActor>) -> Vec<u8> {
+        m.keys().map(|k| *k.val).collect()
+    }
+
+    let mut lru = Map::with_capacity_policy(2, EvictionPolicy::LeastRecentlyUpdated);
+    let mut fifo = Map::with_capacity_policy(2, EvictionPolicy::FirstCreated);
+    for m in [&mut lru, &mut fifo] {
+        write(m, 1);
+        write(m, 2);
+        write(m, 1);
+        write(m, 3);
+    }
+    assert_eq!(keys(&lru), vec![1, 3]);
+    assert_eq!(keys(&fifo), vec![2, 3]);
+
+    // removed entries free up capacity
+    lru.apply(lru.rm(1, lru.get(&1).derive_rm_ctx()));
+    write(&mut lru, 4);
+    assert_eq!(keys(&lru), vec![3, 4]);
+
+    // entries arriving through a merge are bounded too
+    let mut other: Map<u8, MVReg<u8, TActor>, TActor> = Map::new();
+    for key in 5..=7 {
+        other.apply(
+            other.update(key, other.read_ctx().derive_add_ctx(2), |reg, ctx| {
+                reg.write(key, ctx)
+            }),
+        );
+    }
+    lru.merge(other);
+    assert_eq!(lru.len().val, 2);
+}
+
+#[test]
+fn test_entry_clock_removes_only_observed_version() {
+    let mut m1: Map<u8, MVReg<u8, TActor>, TActor> = Map::new();
+    assert_eq!(m1.entry_clock(&1), None);
+
+    m1.apply(m1.update(1, m1.get(&1).derive_add_ctx(1), |reg, ctx| {
+        reg.write(10, ctx)
+    }));
+    let observed = m1.entry_clock(&1).unwrap();
+    assert_eq!(observed, Dot::new(1, 1).into());
+
+    // a concurrent write from another replica
+    let mut m2 = m1.clone();
+    m2.apply(m2.update(1, m2.get(&1).derive_add_ctx(2), |reg, ctx| {
+        reg.write(20, ctx)
+    }));
+
+    // remove exactly the version we observed
+    m1.apply(m1.rm(1, RmCtx { clock: observed }));
+    assert_eq!(m1.entry_clock(&1), None);
+
+    m1.merge(m2);
+    assert_eq!(m1.entry_clock(&1), Some(Dot::new(2, 1).into()));
+    assert_eq!(m1.get(&1).val.map(|r| r.read().val), Some(vec![20]));
 }
 
 #[test]
@@ -260,6 +1730,38 @@ fn test_op_deferred_remove() {
     assert_eq!(m1, m3);
 }
 
+#[test]
+fn test_op_applies_pending_remove_covering_its_dot() {
+    let mut m: Map<u8, MVReg<u8, u8>, u8> = Map::new();
+    let up0 = m.update(0, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(0, ctx)
+    });
+    let up2 = m.update(2, m.read_ctx().derive_add_ctx(2), |reg, ctx| {
+        reg.write(2, ctx)
+    });
+    let rm = map::Op::Rm {
+        clock: vec![Dot::new(1, 1), Dot::new(2, 1)].into_iter().collect(),
+        keyset: vec![0, 1].into_iter().collect(),
+    };
+    m.apply(rm.clone());
+
+    // the remove covers this write but not yet our whole clock
+    m.apply(up0.clone());
+    assert_eq!(m.get(&0).val, None);
+    let up1 = m.update(1, m.read_ctx().derive_add_ctx(1), |reg, ctx| {
+        reg.write(1, ctx)
+    });
+    m.apply(up1.clone());
+    assert_eq!(m.get(&1).val.map(|r| r.read().val), Some(vec![1]));
+
+    m.apply(up2.clone());
+    let mut expected: Map<u8, MVReg<u8, u8>, u8> = Map::new();
+    for op in [up0, up1, up2, rm] {
+        expected.apply(op);
+    }
+    assert_eq!(m, expected);
+}
+
 #[test]
 fn test_merge_deferred_remove() {
     let mut m1: Map<u8, MVReg<u8, u8>, u8> = Map::new();
@@ -574,6 +2076,24 @@ mod prop_tests {
         TestResult::from_bool(m1 == m_merged && m2 == m_merged)
     }
 
+    #[quickcheck]
+    fn prop_apply_shuffled_matches_in_order(
+        ops1_prim: (u8, Vec<OpMaterial>),
+        ops2_prim: (u8, Vec<OpMaterial>),
+        seed: u64,
+    ) -> TestResult {
+        let ops1 = build_ops(ops1_prim);
+        let ops2 = build_ops(ops2_prim);
+
+        if ops1.0 == ops2.0 {
+            return TestResult::discard();
+        }
+
+        let mut m: TMap = Map::new();
+        m.apply_shuffled(ops1.1.into_iter().chain(ops2.1).collect(), seed);
+        TestResult::passed()
+    }
+
     #[quickcheck]
     fn prop_op_exchange_converges(
         ops1_prim: (u8, Vec<OpMaterial>),
@@ -790,6 +2310,113 @@ mod prop_tests {
         m == m_snapshot
     }
 
+    #[quickcheck]
+    fn prop_merge_with_empty_is_identity(ops_prim: (u8, Vec<OpMaterial>)) -> bool {
+        let (_, ops) = build_ops(ops_prim);
+        let mut m = TMap::new();
+        apply_ops(&mut m, &ops);
+
+        let mut m_merged = m.clone();
+        m_merged.merge(TMap::new());
+        let mut empty_merged = TMap::new();
+        empty_merged.merge(m.clone());
+
+        m_merged == m && empty_merged == m
+    }
+
+    #[quickcheck]
+    fn prop_merged_with_same_as_merge(
+        ops1_prim: (u8, Vec<OpMaterial>),
+        ops2_prim: (u8, Vec<OpMaterial>),
+    ) -> bool {
+        let (_, ops1) = build_ops(ops1_prim);
+        let (_, ops2) = build_ops(ops2_prim);
+
+        let mut m1 = TMap::new();
+        let mut m2 = TMap::new();
+        apply_ops(&mut m1, &ops1);
+        apply_ops(&mut m2, &ops2);
+
+        let before = m1.clone();
+        let preview = m1.merged_with(&m2);
+        let untouched = m1 == before;
+        m1.merge(m2);
+
+        untouched && preview == m1
+    }
+
+    #[cfg(feature = "json")]
+    #[quickcheck]
+    fn prop_op_log_round_trip(
+        ops1_prim: (u8, Vec<OpMaterial>),
+        ops2_prim: (u8, Vec<OpMaterial>),
+    ) -> bool {
+        let (_, ops1) = build_ops(ops1_prim);
+        let (_, ops2) = build_ops(ops2_prim);
+        let mut log = ops1;
+        log.extend(ops2);
+
+        let mut live = TMap::new();
+        apply_ops(&mut live, &log);
+
+        let mut bytes = Vec::new();
+        TMap::write_op_log(&mut bytes, &log).unwrap();
+        TMap::from_op_log(bytes.as_slice()).unwrap() == live
+    }
+
+    #[quickcheck]
+    fn prop_compact_ops_same_state(
+        ops1_prim: (u8, Vec<OpMaterial>),
+        ops2_prim: (u8, Vec<OpMaterial>),
+        redelivered: usize,
+    ) -> bool {
+        let (_, ops1) = build_ops(ops1_prim);
+        let (_, ops2) = build_ops(ops2_prim);
+
+        // an op log with interleaved actors and some ops delivered twice
+        let mut log = ops1.clone();
+        log.extend(ops2);
+        log.extend(ops1.iter().take(redelivered % (ops1.len() + 1)).cloned());
+
+        let compacted = TMap::compact_ops(&log);
+
+        let mut m_log = TMap::new();
+        apply_ops(&mut m_log, &log);
+        let mut m_compacted = TMap::new();
+        apply_ops(&mut m_compacted, &compacted);
+
+        compacted.len() <= log.len() && m_log == m_compacted
+    }
+
+    #[quickcheck]
+    fn prop_sync_payload_same_as_merge(
+        ops_base_prim: (u8, Vec<OpMaterial>),
+        ops1_prim: (u8, Vec<OpMaterial>),
+        ops2_prim: (u8, Vec<OpMaterial>),
+    ) -> TestResult {
+        let ops_base = build_ops(ops_base_prim);
+        let ops1 = build_ops(ops1_prim);
+        let ops2 = build_ops(ops2_prim);
+
+        if ops_base.0 == ops1.0 || ops_base.0 == ops2.0 || ops1.0 == ops2.0 {
+            return TestResult::discard();
+        }
+
+        let mut m1: TMap = Map::new();
+        apply_ops(&mut m1, &ops_base.1);
+        let mut m2 = m1.clone();
+
+        apply_ops(&mut m1, &ops1.1);
+        apply_ops(&mut m2, &ops2.1);
+
+        let mut m2_merged = m2.clone();
+        m2_merged.merge(m1.clone());
+
+        m2.sync_apply(m1.sync_payload(&m2.read_ctx().add_clock));
+
+        TestResult::from_bool(m2 == m2_merged)
+    }
+
     #[quickcheck]
     fn prop_reset_remove_with_empty_vclock_is_nop(ops_prim: (u8, Vec<OpMaterial>)) -> bool {
         let ops = build_ops(ops_prim);
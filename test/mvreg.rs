@@ -62,6 +62,28 @@ fn test_concurrent_update_with_same_value_dont_collapse_on_apply() {
     );
 }
 
+#[test]
+fn test_clock_and_causal_cmp() {
+    let mut r1 = MVReg::new();
+    assert_eq!(r1.clock(), VClock::new());
+
+    r1.apply(r1.write(1, r1.read_ctx().derive_add_ctx("A")));
+    let mut r2 = r1.clone();
+    assert_eq!(r1.causal_cmp(&r2), Some(std::cmp::Ordering::Equal));
+
+    r2.apply(r2.write(2, r2.read_ctx().derive_add_ctx("B")));
+    assert_eq!(r2.clock(), r2.read().add_clock);
+    assert_eq!(r1.causal_cmp(&r2), Some(std::cmp::Ordering::Less));
+
+    // r2 dominates r1, merging r1 into it changes nothing
+    let before = r2.clone();
+    r2.merge(r1.clone());
+    assert_eq!(r2, before);
+
+    r1.apply(r1.write(3, r1.read_ctx().derive_add_ctx("A")));
+    assert_eq!(r1.causal_cmp(&r2), None);
+}
+
 #[test]
 fn test_multi_val() {
     let mut r1 = MVReg::new();
@@ -304,3 +326,132 @@ mod prop_tests {
         false
     }
 }
+
+#[test]
+fn test_concurrent_values_have_a_stable_order() {
+    let base = MVReg::new();
+    let ops: Vec<_> = vec![(3u8, "c"), (1, "a"), (2, "b")]
+        .into_iter()
+        .map(|(actor, val)| base.write(val, base.read_ctx().derive_add_ctx(actor)))
+        .collect();
+
+    let mut forward = base.clone();
+    for op in ops.iter().cloned() {
+        forward.apply(op);
+    }
+    let mut backward = base.clone();
+    for op in ops.iter().rev().cloned() {
+        backward.apply(op);
+    }
+    assert_eq!(forward.read().val, vec!["a", "b", "c"]);
+    assert_eq!(backward.read().val, vec!["a", "b", "c"]);
+
+    let replicas: Vec<MVReg<_, _>> = ops
+        .into_iter()
+        .map(|op| {
+            let mut r = base.clone();
+            r.apply(op);
+            r
+        })
+        .collect();
+    let mut merged_forward = base.clone();
+    for r in replicas.iter().cloned() {
+        merged_forward.merge(r);
+    }
+    let mut merged_backward = base;
+    for r in replicas.into_iter().rev() {
+        merged_backward.merge(r);
+    }
+    assert_eq!(merged_forward.read().val, vec!["a", "b", "c"]);
+    assert_eq!(merged_backward.read().val, vec!["a", "b", "c"]);
+}
+
+#[test]
+fn test_merge_tracking_reports_changed_values() {
+    let mut a = MVReg::new();
+    a.apply(a.write(1, a.read_ctx().derive_add_ctx("A")));
+    let mut b = a.clone();
+    let mut c = a.clone();
+
+    b.apply(b.write(2, b.read_ctx().derive_add_ctx("B")));
+    let report = a.merge_tracking(b.clone());
+    assert_eq!(report.added, vec![2]);
+    assert_eq!(report.dropped, vec![1]);
+
+    // a concurrent write shows up next to the value we already hold
+    c.apply(c.write(3, c.read_ctx().derive_add_ctx("C")));
+    let report = a.merge_tracking(c);
+    assert_eq!(report.added, vec![3]);
+    assert!(report.dropped.is_empty());
+    assert_eq!(a.read().val, vec![2, 3]);
+
+    // merging state we've already seen changes nothing
+    let report = a.merge_tracking(b);
+    assert!(report.added.is_empty() && report.dropped.is_empty());
+}
+
+#[test]
+fn test_gc_shrinks_clocks_and_keeps_values() {
+    let mut a = MVReg::new();
+    for i in 0..20u8 {
+        let actor = i % 3;
+        a.apply(a.write(i, a.read_ctx().derive_add_ctx(actor)));
+    }
+    let mut b = a.clone();
+    a.apply(a.write(100, a.read_ctx().derive_add_ctx(0)));
+    b.apply(b.write(200, b.read_ctx().derive_add_ctx(1)));
+    a.merge(b);
+    assert_eq!(a.read().val, vec![200, 100]);
+    assert_eq!(a.clock(), VClock::from_pairs([(0, 8), (1, 8), (2, 6)]));
+
+    // every replica has seen the writes before the concurrent ones
+    let before = a.heap_size();
+    a.gc(&VClock::from_pairs([(0, 7), (1, 7), (2, 6)]));
+    assert!(a.heap_size() < before);
+    assert_eq!(a.clock(), VClock::from_pairs([(0, 8), (1, 8), (2, 6)]));
+    // both values survive, their order follows the trimmed clocks
+    assert_eq!(a.read().val, vec![100, 200]);
+
+    // a write that has seen both values still replaces them
+    a.apply(a.write(42, a.read_ctx().derive_add_ctx(2)));
+    assert_eq!(a.read().val, vec![42]);
+}
+
+#[test]
+fn test_gc_at_different_times_converges() {
+    let mut x = MVReg::new();
+    x.apply(x.write("v1", x.read_ctx().derive_add_ctx('a')));
+    x.apply(x.write("v2", x.read_ctx().derive_add_ctx('a')));
+    let mut y = x.clone();
+
+    // x collects the stable writes, y hasn't yet
+    x.gc(&x.clock());
+    let op = x.write("v3", x.read_ctx().derive_add_ctx('a'));
+    assert_eq!(
+        op,
+        Op::Put {
+            clock: Dot::new('a', 3).into(),
+            val: "v3"
+        }
+    );
+    x.apply(op.clone());
+    y.apply(op);
+    assert_eq!(x.read().val, vec!["v3"]);
+    assert_eq!(y.read().val, vec!["v3"]);
+
+    // y collects later and both keep writing concurrently
+    y.gc(&y.clock());
+    let op_x = x.write("x4", x.read_ctx().derive_add_ctx('a'));
+    let op_y = y.write("y4", y.read_ctx().derive_add_ctx('b'));
+    let mut merged_x = x.clone();
+    x.apply(op_x.clone());
+    y.apply(op_y.clone());
+    x.apply(op_y);
+    y.apply(op_x);
+    assert_eq!(x, y);
+    assert_eq!(x.read().val, vec!["y4", "x4"]);
+
+    merged_x.merge(y.clone());
+    y.merge(merged_x);
+    assert_eq!(x.read().val, y.read().val);
+}
@@ -1,6 +1,6 @@
-use crdts::orswot::Op;
-use crdts::{CmRDT, CvRDT, Dot, Map, Orswot, VClock};
-use std::collections::HashSet;
+use crdts::orswot::{Op, Presence};
+use crdts::{CmRDT, CvRDT, Dot, LogicalEq, Map, Orswot, VClock};
+use std::collections::{BTreeSet, HashSet};
 use std::iter::once;
 
 type Member = u8;
@@ -20,7 +20,177 @@ fn weird_highlight_1() {
     assert!(a.read().val.is_empty());
 }
 
-///
+#[test]
+fn test_len_and_is_empty() {
+    let mut a = Orswot::new();
+    assert_eq!(a.len().val, 0);
+    assert!(a.is_empty().val);
+
+    a.apply(a.add_all(vec![1, 2, 3], a.read_ctx().derive_add_ctx("A")));
+    assert_eq!(a.len().val, 3);
+    assert!(!a.is_empty().val);
+    assert_eq!(a.len().add_clock, a.clock());
+    assert_eq!(a.len().rm_clock, a.clock());
+
+    a.apply(a.rm(2, a.contains(&2).derive_rm_ctx()));
+    assert_eq!(a.len().val, 2);
+}
+
+#[test]
+fn test_is_default() {
+    let mut a = Orswot::new();
+    assert!(a.is_default());
+
+    a.apply(a.add(1, a.read_ctx().derive_add_ctx("A")));
+    let rm_ctx = a.contains(&1).derive_rm_ctx();
+    a.apply(a.rm(1, rm_ctx.clone()));
+    assert!(a.is_empty().val);
+    assert!(!a.is_default());
+
+    // a remove of an unseen add is deferred, so the set is no longer pristine
+    let mut b: Orswot<u8, &str> = Orswot::new();
+    b.apply(b.rm(1, rm_ctx));
+    assert!(b.is_empty().val);
+    assert!(!b.is_default());
+}
+
+#[test]
+fn test_reapplied_add_is_ignored() {
+    let mut a = Orswot::new();
+    let op = a.add_all(vec![1, 2], a.read_ctx().derive_add_ctx("A"));
+    a.apply(op.clone());
+    let applied_once = a.clone();
+
+    a.apply(op.clone());
+    assert_eq!(a, applied_once);
+    assert_eq!(a.clock(), VClock::from(Dot::new("A", 1)));
+
+    // a late duplicate doesn't bring back a removed member
+    a.apply(a.rm(1, a.contains(&1).derive_rm_ctx()));
+    a.apply(op);
+    assert_eq!(a.read().val, once(2).collect());
+}
+
+#[test]
+fn test_explain_deferred_remove() {
+    let mut a = Orswot::new();
+    let add = a.add(1, a.read_ctx().derive_add_ctx("A"));
+    a.apply(add.clone());
+    let rm = a.rm(1, a.contains(&1).derive_rm_ctx());
+
+    // b sees the remove before the add it covers
+    let mut b: Orswot<u8, &str> = Orswot::new();
+    b.apply(b.add(2, b.read_ctx().derive_add_ctx("B")));
+    b.apply(rm);
+
+    let explanation = b.explain(&1);
+    assert_eq!(explanation.presence, Presence::Deferred);
+    assert!(explanation.member_clock.is_empty());
+    assert_eq!(
+        explanation.pending_removes,
+        vec![VClock::from(Dot::new("A", 1))]
+    );
+    assert_eq!(b.explain(&2).presence, Presence::Live);
+    assert_eq!(b.explain(&2).member_clock, VClock::from(Dot::new("B", 1)));
+
+    // once the add arrives the deferred remove drops it
+    b.apply(add);
+    let explanation = b.explain(&1);
+    assert_eq!(explanation.presence, Presence::Dropped);
+    assert!(explanation.pending_removes.is_empty());
+    assert_eq!(
+        explanation.to_string(),
+        "dropped: no dots remain for it under <A:1, B:1>"
+    );
+}
+
+#[test]
+fn test_logically_eq_ignores_history() {
+    let mut a: Orswot<u8, u8> = Orswot::new();
+    a.apply(a.add(1, a.read_ctx().derive_add_ctx(1)));
+
+    let mut b = a.clone();
+    b.apply(b.add(2, b.read_ctx().derive_add_ctx(2)));
+    b.apply(b.rm(2, b.contains(&2).derive_rm_ctx()));
+
+    assert_ne!(a, b);
+    assert!(a.logically_eq(&b));
+
+    b.apply(b.add(3, b.read_ctx().derive_add_ctx(2)));
+    assert!(!a.logically_eq(&b));
+}
+
+#[test]
+fn test_concurrent_add_wins_over_remove() {
+    let mut a = Orswot::new();
+    a.apply(a.add(1, a.read_ctx().derive_add_ctx("A")));
+    let mut b = a.clone();
+
+    // a removes the member while b concurrently re-adds it
+    let rm = a.rm(1, a.contains(&1).derive_rm_ctx());
+    let add = b.add(1, b.read_ctx().derive_add_ctx("B"));
+    a.apply(rm.clone());
+    b.apply(add.clone());
+    assert!(!a.contains(&1).val);
+
+    let mut ab = a.clone();
+    ab.merge(b.clone());
+    let mut ba = b.clone();
+    ba.merge(a.clone());
+    assert!(ab.contains(&1).val);
+    assert_eq!(ab, ba);
+
+    // exchanging the ops instead of the state gives the same answer
+    a.apply(add);
+    b.apply(rm);
+    assert!(a.contains(&1).val);
+    assert!(b.contains(&1).val);
+}
+
+#[test]
+fn test_rm_if_spares_concurrent_adds() {
+    let mut a = Orswot::new();
+    a.apply(a.add_all(1..=6, a.read_ctx().derive_add_ctx("A")));
+    let mut b = a.clone();
+
+    a.apply(a.rm_if(a.read_ctx().derive_rm_ctx(), |m| m % 2 == 0));
+    assert_eq!(a.read().val, vec![1, 3, 5].into_iter().collect());
+
+    // concurrently b re-adds a doomed member and adds a new one matching the predicate
+    b.apply(b.add_all(vec![2, 8], b.read_ctx().derive_add_ctx("B")));
+
+    a.merge(b);
+    assert_eq!(a.read().val, vec![1, 2, 3, 5, 8].into_iter().collect());
+}
+
+#[test]
+fn test_retain() {
+    let mut a = Orswot::new();
+    a.apply(a.add_all(1..=4, a.read_ctx().derive_add_ctx("A")));
+    let clock = a.clock();
+
+    a.retain(|m| *m > 2);
+    assert_eq!(a.read().val, vec![3, 4].into_iter().collect());
+    assert_eq!(a.clock(), clock);
+}
+
+#[test]
+fn test_from_reads_into_set_round_trip() {
+    let members: BTreeSet<u8> = vec![3, 1, 4, 1, 5, 9].into_iter().collect();
+    let set = Orswot::from_reads("A", vec![3, 1, 4, 1, 5, 9]);
+
+    assert_eq!(set.clock(), VClock::from(Dot::new("A", 5)));
+    assert_eq!(set.read().val, members.iter().cloned().collect());
+
+    // the seed behaves like any other replica
+    let mut other = set.clone();
+    other.apply(other.rm(9, other.contains(&9).derive_rm_ctx()));
+    let mut merged = set;
+    merged.merge(other);
+
+    assert_eq!(merged.into_set(), vec![1, 3, 4, 5].into_iter().collect());
+}
+
 #[test]
 fn adds_dont_destroy_causality() {
     let mut a = Orswot::new();
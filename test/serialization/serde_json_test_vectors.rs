@@ -95,7 +95,7 @@ fn gen_map() -> Map<String, MVReg<u64, String>, String> {
     assert_eq!(m.get(&"age".into()).val.unwrap().read().val, vec![34]);
     assert_eq!(
         m.get(&"height".into()).val.unwrap().read().val,
-        vec![152, 156]
+        vec![156, 152]
     );
     m
 }
@@ -122,7 +122,7 @@ fn gen_mvreg() -> MVReg<u64, String> {
     let add_ctx_alice = reg.read_ctx().derive_add_ctx("alice".into());
     reg.apply(reg.write(12, add_ctx_bob));
     reg.apply(reg.write(21, add_ctx_alice));
-    assert_eq!(reg.read().val, vec![12, 21]);
+    assert_eq!(reg.read().val, vec![21, 12]);
     reg
 }
 
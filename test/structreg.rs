@@ -0,0 +1,56 @@
+use crdts::{CmRDT, CvRDT, MVReg, StructReg};
+
+type TReg = StructReg<&'static str, u8, u8>;
+
+#[test]
+fn test_concurrent_edits_to_disjoint_fields_dont_conflict() {
+    let mut a = TReg::new();
+    a.apply(a.set("x", 1, a.read_ctx().derive_add_ctx(1)));
+    a.apply(a.set("y", 1, a.read_ctx().derive_add_ctx(1)));
+    let mut b = a.clone();
+
+    a.apply(a.set("x", 2, a.read_ctx().derive_add_ctx(1)));
+    b.apply(b.set("y", 3, b.read_ctx().derive_add_ctx(2)));
+
+    let mut merged = a.clone();
+    merged.merge(b.clone());
+    b.merge(a);
+    assert_eq!(merged, b);
+    assert_eq!(merged.get(&"x").val, vec![2]);
+    assert_eq!(merged.get(&"y").val, vec![3]);
+
+    // the same edits to a struct in a single register do conflict
+    let mut reg_a = MVReg::new();
+    reg_a.apply(reg_a.write((1, 1), reg_a.read_ctx().derive_add_ctx(1)));
+    let mut reg_b = reg_a.clone();
+    reg_a.apply(reg_a.write((2, 1), reg_a.read_ctx().derive_add_ctx(1)));
+    reg_b.apply(reg_b.write((1, 3), reg_b.read_ctx().derive_add_ctx(2)));
+    reg_a.merge(reg_b);
+    assert_eq!(reg_a.read().val.len(), 2);
+}
+
+#[test]
+fn test_concurrent_edits_to_same_field_conflict() {
+    let mut a = TReg::new();
+    let mut b = a.clone();
+
+    a.apply(a.set("x", 1, a.read_ctx().derive_add_ctx(1)));
+    b.apply(b.set("x", 2, b.read_ctx().derive_add_ctx(2)));
+    a.merge(b);
+
+    let mut vals = a.get(&"x").val;
+    vals.sort();
+    assert_eq!(vals, vec![1, 2]);
+
+    // a write that has seen both values resolves the conflict
+    a.apply(a.set("x", 3, a.get(&"x").derive_add_ctx(1)));
+    assert_eq!(a.get(&"x").val, vec![3]);
+}
+
+#[test]
+fn test_unset() {
+    let mut a = TReg::new();
+    a.apply(a.set("x", 1, a.read_ctx().derive_add_ctx(1)));
+    a.apply(a.unset("x", a.get(&"x").derive_rm_ctx()));
+    assert_eq!(a.get(&"x").val, Vec::<u8>::new());
+}
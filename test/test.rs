@@ -1,11 +1,16 @@
+mod dotstore;
 #[cfg(feature = "num")]
 mod glist;
+#[cfg(feature = "json")]
+mod json;
 #[cfg(feature = "num")]
 mod list;
+mod lwwmap;
 mod map;
 #[cfg(feature = "merkle")]
 mod merkle_reg;
 mod mvreg;
 mod orswot;
 mod serialization;
+mod structreg;
 mod vclock;
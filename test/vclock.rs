@@ -71,6 +71,98 @@ fn test_merge_same_id() {
     assert_eq!(a.get(&3), 1);
 }
 
+#[test]
+fn test_clamp() {
+    let frontier = VClock::from_pairs(vec![(1, 3), (2, 3), (4, 2)]);
+
+    // actor above the frontier is capped, below is untouched,
+    // missing from the frontier is kept, missing from self isn't added
+    let mut a = VClock::from_pairs(vec![(1, 5), (2, 1), (3, 7)]);
+    a.clamp(&frontier);
+    assert_eq!(a, VClock::from_pairs(vec![(1, 3), (2, 1), (3, 7)]));
+
+    // clamping to an empty frontier is a no-op
+    let mut b = a.clone();
+    b.clamp(&VClock::new());
+    assert_eq!(b, a);
+
+    // clamping is idempotent and never grows the clock
+    let mut c = a.clone();
+    c.clamp(&frontier);
+    assert_eq!(c, a);
+    assert!(c <= VClock::from_pairs(vec![(1, 5), (2, 1), (3, 7)]));
+}
+
+#[test]
+fn test_pairs_round_trip() {
+    let rows: Vec<(u8, u64)> = vec![(1, 4), (3, 1), (2, 2), (1, 2), (4, 0)];
+    let clock = VClock::from_pairs(rows);
+    assert_eq!(
+        clock,
+        vec![Dot::new(1, 4), Dot::new(2, 2), Dot::new(3, 1)]
+            .into_iter()
+            .collect()
+    );
+
+    let stored: Vec<(u8, u64)> = clock.clone().into_pairs().collect();
+    assert_eq!(stored, vec![(1, 4), (2, 2), (3, 1)]);
+    assert_eq!(VClock::from_pairs(stored), clock);
+
+    let dot = Dot::new(7, 9);
+    let pair: (u8, u64) = dot.into();
+    assert_eq!(Dot::from(pair), dot);
+}
+
+#[test]
+fn test_checked_subtract() {
+    let a: VClock<u8> = vec![Dot::new(1, 4), Dot::new(2, 2)].into_iter().collect();
+    let b: VClock<u8> = vec![Dot::new(1, 4), Dot::new(2, 1)].into_iter().collect();
+    assert_eq!(a.checked_subtract(&b), Ok(Dot::new(2, 2).into()));
+    assert_eq!(a.checked_subtract(&b), Ok(a.clone_without(&b)));
+
+    let c: VClock<u8> = vec![Dot::new(1, 5), Dot::new(2, 1), Dot::new(3, 1)]
+        .into_iter()
+        .collect();
+    let report = a.checked_subtract(&c).unwrap_err();
+    assert_eq!(
+        report.ahead,
+        vec![Dot::new(1, 5), Dot::new(3, 1)].into_iter().collect()
+    );
+}
+
+#[test]
+fn test_divergence_of_concurrent_clocks() {
+    let a = VClock::from_pairs(vec![(1u8, 5), (2, 3), (3, 1)]);
+    let b = VClock::from_pairs(vec![(1u8, 2), (2, 3), (4, 7)]);
+    assert!(a.concurrent(&b));
+
+    let (a_ahead, b_ahead) = a.divergence(&b);
+    assert_eq!(a_ahead, VClock::from_pairs(vec![(1, 5), (3, 1)]));
+    assert_eq!(b_ahead, VClock::from_pairs(vec![(4, 7)]));
+
+    // each side catches up by merging in what the other is ahead on
+    let (mut a_synced, mut b_synced) = (a.clone(), b.clone());
+    a_synced.merge(b_ahead);
+    b_synced.merge(a_ahead);
+    assert_eq!(a_synced, b_synced);
+
+    let (x, y) = a.divergence(&a);
+    assert!(x.is_empty() && y.is_empty());
+}
+
+#[test]
+fn test_apply_dot_reports_advance() {
+    let mut a = VClock::new();
+    let dot = a.inc(1);
+
+    assert!(a.apply_dot(dot));
+    assert!(!a.apply_dot(dot));
+    assert_eq!(a.get(&1), 1);
+
+    assert!(a.apply_dot(Dot::new(2, 3)));
+    assert!(!a.apply_dot(Dot::new(2, 2)));
+}
+
 #[test]
 #[allow(clippy::neg_cmp_op_on_partial_ord)]
 fn test_vclock_ordering() {
@@ -138,6 +230,34 @@ mod prop_tests {
 
     use quickcheck_macros::quickcheck;
 
+    #[quickcheck]
+    fn prop_delta_from_base_rebuilds_join(base: VClock<u8>, clock: VClock<u8>) -> bool {
+        let delta = clock.clone_without(&base);
+
+        let mut rebuilt = base.clone();
+        rebuilt.merge(delta.clone());
+        let mut joined = base;
+        joined.merge(clock.clone());
+
+        rebuilt == joined && delta.dots.len() <= clock.dots.len()
+    }
+
+    #[quickcheck]
+    fn prop_canonical_clocks_from_same_dots_are_equal(dots: Vec<Dot<u8>>, zeros: Vec<u8>) -> bool {
+        let forward: VClock<u8> = dots.clone().into_iter().collect();
+        let mut padded: VClock<u8> = dots.into_iter().rev().collect();
+        for actor in zeros {
+            padded.dots.entry(actor).or_insert(0);
+        }
+
+        let mut merged = VClock::new();
+        merged.merge(padded.clone());
+        let parsed: VClock<u8> =
+            serde_json::from_str(&serde_json::to_string(&padded).unwrap()).unwrap();
+        padded.canonicalize();
+        padded == forward && merged == forward && parsed == forward
+    }
+
     #[quickcheck]
     fn prop_into_iter_produces_same_vclock(clock: VClock<u8>) -> bool {
         clock == clock.clone().into_iter().collect()
@@ -209,3 +329,161 @@ mod prop_tests {
         }
     }
 }
+
+#[test]
+fn test_intersection_disjoint_actors() {
+    let a = VClock::from_pairs(vec![(1u8, 3), (2, 1)]);
+    let b = VClock::from_pairs(vec![(3u8, 3), (4, 1)]);
+    assert!(VClock::intersection(&a, &b).is_empty());
+}
+
+#[test]
+fn test_intersection_shared_actors_with_differing_counters() {
+    let a = VClock::from_pairs(vec![(1u8, 3), (2, 1), (3, 7)]);
+    let b = VClock::from_pairs(vec![(1u8, 3), (2, 5), (3, 2)]);
+    assert_eq!(
+        VClock::intersection(&a, &b),
+        VClock::from_pairs(vec![(1u8, 3)])
+    );
+    assert_eq!(VClock::intersection(&a, &b), VClock::intersection(&b, &a));
+}
+
+#[test]
+fn test_intersection_identical_clocks() {
+    let a = VClock::from_pairs(vec![(1u8, 3), (2, 1)]);
+    assert_eq!(VClock::intersection(&a, &a), a);
+}
+
+#[test]
+fn test_syntactic_cmp_is_not_causal() {
+    use std::cmp::Ordering;
+
+    let a = VClock::from_pairs(vec![(1u8, 1), (2, 2)]);
+    let b = VClock::from_pairs(vec![(1u8, 2)]);
+    let c = VClock::from_pairs(vec![(1u8, 1), (2, 2), (3, 1)]);
+
+    // concurrent clocks still get a total, antisymmetric order
+    assert_eq!(a.partial_cmp(&b), None);
+    assert_eq!(a.syntactic_cmp(&b), Ordering::Less);
+    assert_eq!(b.syntactic_cmp(&a), Ordering::Greater);
+
+    // b is concurrent with c yet sorts after it, even though c dominates a
+    assert_eq!(a.partial_cmp(&c), Some(Ordering::Less));
+    assert_eq!(a.syntactic_cmp(&c), Ordering::Less);
+    assert_eq!(b.partial_cmp(&c), None);
+    assert_eq!(b.syntactic_cmp(&c), Ordering::Greater);
+
+    // identical clocks are equal under both orders
+    assert_eq!(a.partial_cmp(&a.clone()), Some(Ordering::Equal));
+    assert_eq!(a.syntactic_cmp(&a.clone()), Ordering::Equal);
+
+    // a dominating clock can sort first
+    let d = VClock::from_pairs(vec![(0u8, 1), (1, 1)]);
+    let e = VClock::from_pairs(vec![(1u8, 1)]);
+    assert_eq!(d.partial_cmp(&e), Some(Ordering::Greater));
+    assert_eq!(d.syntactic_cmp(&e), Ordering::Less);
+}
+
+#[test]
+fn test_dot_is_current_and_inc_is_next() {
+    let mut clock = VClock::new();
+    assert_eq!(clock.dot("A"), Dot::new("A", 0));
+    assert_eq!(clock.inc("A"), Dot::new("A", 1));
+
+    clock.apply(clock.inc("A"));
+    clock.apply(clock.inc("A"));
+    assert_eq!(clock.dot("A"), Dot::new("A", 2));
+    assert_eq!(clock.inc("A"), Dot::new("A", 3));
+    assert_eq!(clock.dot("B"), Dot::new("B", 0));
+}
+
+#[test]
+fn test_stability_under_staggered_acks() {
+    use crdts::vclock::StabilityTracker;
+
+    let mut tracker = StabilityTracker::new(vec![1u8, 2, 3]);
+    let op = Dot::new(1, 2);
+    assert!(!tracker.is_stable(&op));
+
+    tracker.ack(1, VClock::from_pairs(vec![(1, 2)]));
+    tracker.ack(2, VClock::from_pairs(vec![(1, 1), (2, 4)]));
+    assert!(!tracker.is_stable(&op));
+
+    tracker.ack(2, VClock::from_pairs(vec![(1, 3)]));
+    tracker.ack(3, VClock::from_pairs(vec![(1, 2), (2, 1)]));
+    assert!(tracker.is_stable(&op));
+    assert!(!tracker.is_stable(&Dot::new(1, 3)));
+    assert_eq!(tracker.stable_clock(), VClock::from_pairs(vec![(1, 2)]));
+
+    // a stale ack doesn't make an event unstable again
+    tracker.ack(3, VClock::from_pairs(vec![(1, 1)]));
+    assert!(tracker.is_stable(&op));
+
+    // a newly seen actor has acknowledged nothing yet
+    tracker.ack(4, VClock::new());
+    assert!(!tracker.is_stable(&op));
+    assert!(tracker.stable_clock().is_empty());
+}
+
+#[test]
+fn test_gapped_clock_reports_missing_dots() {
+    use crdts::vclock::GappedClock;
+
+    let mut seen = GappedClock::new();
+    for (actor, counter) in [(1u8, 5), (1, 2), (2, 3), (1, 1), (1, 7), (2, 1)] {
+        seen.apply(Dot::new(actor, counter));
+    }
+    assert_eq!(seen.clock(), &VClock::from_pairs([(1, 2), (2, 1)]));
+    assert_eq!(
+        seen.missing_dots(),
+        vec![
+            Dot::new(1, 3),
+            Dot::new(1, 4),
+            Dot::new(1, 6),
+            Dot::new(2, 2)
+        ]
+    );
+    assert!(seen.contains(&Dot::new(1, 5)));
+    assert!(!seen.contains(&Dot::new(1, 6)));
+
+    // filling the gaps folds the dots that arrived early into the clock
+    for dot in seen.missing_dots() {
+        seen.apply(dot);
+    }
+    assert_eq!(seen.clock(), &VClock::from_pairs([(1, 7), (2, 3)]));
+    assert!(seen.missing_dots().is_empty());
+}
+
+#[test]
+fn test_split_at_isolates_one_actor() {
+    let clock = VClock::from_pairs([(1u8, 4), (2, 7), (3, 1)]);
+
+    let (dot, rest) = clock.split_at(&2);
+    assert_eq!(dot, Some(Dot::new(2, 7)));
+    assert_eq!(rest, VClock::from_pairs([(1, 4), (3, 1)]));
+
+    // the halves join back into the original clock
+    let mut joined = rest;
+    joined.apply(dot.unwrap());
+    assert_eq!(joined, clock);
+
+    assert_eq!(clock.split_at(&9), (None, clock.clone()));
+}
+
+#[test]
+fn test_from_dots_keeps_max_per_actor() {
+    let dots = vec![
+        Dot::new(1u8, 2),
+        Dot::new(2, 4),
+        Dot::new(1, 5),
+        Dot::new(1, 3),
+    ];
+
+    let clock = VClock::from_dots(dots.clone());
+    assert_eq!(clock, VClock::from_pairs([(1, 5), (2, 4)]));
+    assert_eq!(clock, dots.into_iter().collect());
+    assert_eq!(
+        VClock::from(Dot::new(1u8, 5)),
+        VClock::from_dots([Dot::new(1, 5)])
+    );
+}